@@ -0,0 +1,411 @@
+use crate::bot::{get_parent_channel, is_channel_excluded};
+use crate::makers::make_entry;
+use nlp_bot_api::processor::backfill::BackfillCursor;
+use nlp_bot_api::processor::entry::Entry;
+use nlp_bot_api::processor::Processor;
+use serenity::all::{ChannelId, GuildChannel, GuildId, Message, MessageId};
+use serenity::builder::GetMessages;
+use serenity::cache::Cache;
+use serenity::http::{Http, HttpError, StatusCode};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, Semaphore};
+use tokio::task::JoinSet;
+
+const MESSAGE_LIMIT: u8 = 100;
+pub const DEFAULT_CONCURRENCY: usize = 4;
+/// How many times a failed request is retried before the channel is skipped
+const MAX_RETRIES: u32 = 5;
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+/// The JSON error codes Discord uses for channels the bot can't see and for
+/// ones whose history it can't read
+const MISSING_ACCESS_ERROR_CODES: [isize; 2] = [50001, 50013];
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// Returned when a channel is removed or excluded while it is being read
+#[derive(Debug)]
+struct Stopped;
+
+impl fmt::Display for Stopped {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The container was removed or excluded")
+    }
+}
+
+impl std::error::Error for Stopped {}
+
+/// How far reading the message history of a guild got
+#[derive(Clone, Default)]
+pub struct GuildProgress {
+    pub channels_total: usize,
+    pub channels_done: usize,
+    /// Channels which could not be read, e.g. because of missing access
+    pub channels_skipped: usize,
+    pub messages_read: u64,
+    started_at: Option<Instant>,
+}
+
+impl GuildProgress {
+    const fn get_channels_finished(&self) -> usize {
+        self.channels_done + self.channels_skipped
+    }
+
+    pub const fn is_finished(&self) -> bool {
+        self.get_channels_finished() >= self.channels_total
+    }
+
+    /// Estimated from the average time per channel so far
+    pub fn get_eta(&self) -> Option<Duration> {
+        let channels_finished = u32::try_from(self.get_channels_finished()).ok()?;
+        if channels_finished == 0 || self.is_finished() {
+            return None;
+        }
+
+        let channels_remaining =
+            u32::try_from(self.channels_total - self.get_channels_finished()).ok()?;
+        let elapsed = self.started_at?.elapsed();
+
+        Some(elapsed / channels_finished * channels_remaining)
+    }
+}
+
+/// Reads the message history of channels, continuing where the previous run
+/// stopped
+pub struct Backfill {
+    processor: Arc<Processor>,
+    /// Limits how many channels are read at once across all guilds
+    semaphore: Semaphore,
+    progress: Mutex<HashMap<GuildId, GuildProgress>>,
+    /// Guilds which were removed while being read
+    stopped_guild_ids: Mutex<HashSet<GuildId>>,
+    /// The channels being read or waiting to be, and whether they were
+    /// stopped
+    running_channels: Mutex<HashMap<ChannelId, bool>>,
+    /// Whether channels are read from the start, ignoring the stored cursors
+    reread_history: bool,
+}
+
+impl Backfill {
+    pub fn new(processor: Arc<Processor>, concurrency: usize) -> Self {
+        Self {
+            processor,
+            semaphore: Semaphore::new(concurrency.max(1)),
+            progress: Mutex::new(HashMap::new()),
+            stopped_guild_ids: Mutex::new(HashSet::new()),
+            running_channels: Mutex::new(HashMap::new()),
+            reread_history: false,
+        }
+    }
+
+    /// Already stored messages are read again, e.g. to reclassify their
+    /// authors.
+    pub const fn with_reread_history(mut self, reread_history: bool) -> Self {
+        self.reread_history = reread_history;
+        self
+    }
+
+    pub async fn get_progress(&self, guild_id: GuildId) -> Option<GuildProgress> {
+        self.progress.lock().await.get(&guild_id).cloned()
+    }
+
+    async fn start_progress(&self, guild_id: GuildId, channels_count: usize) {
+        self.update_progress(guild_id, |progress| {
+            // Channels added while the guild is being read extend the same run
+            if progress.is_finished() {
+                *progress = GuildProgress {
+                    started_at: Some(Instant::now()),
+                    ..GuildProgress::default()
+                };
+            }
+            progress.channels_total += channels_count;
+        })
+        .await;
+    }
+
+    async fn update_progress(&self, guild_id: GuildId, update: impl FnOnce(&mut GuildProgress)) {
+        update(self.progress.lock().await.entry(guild_id).or_default());
+    }
+
+    /// Stops reading the channels of a guild the bot was removed from. They
+    /// stop after the page being read.
+    pub async fn stop_guild(&self, guild_id: GuildId) {
+        self.stopped_guild_ids.lock().await.insert(guild_id);
+    }
+
+    /// Stops reading a deleted or excluded channel or thread after the page
+    /// being read. Channels which are not being read are left alone.
+    pub async fn stop_channel(&self, channel_id: ChannelId) {
+        if let Some(stopped) = self.running_channels.lock().await.get_mut(&channel_id) {
+            *stopped = true;
+        }
+    }
+
+    async fn is_stopped(&self, guild_id: GuildId, channel_id: ChannelId) -> bool {
+        self.stopped_guild_ids.lock().await.contains(&guild_id)
+            || self.running_channels.lock().await.get(&channel_id) == Some(&true)
+    }
+
+    /// Reads the channels of the guild and waits until all of them are done.
+    pub async fn run(
+        self: Arc<Self>,
+        http: Arc<Http>,
+        cache: Arc<Cache>,
+        guild_id: GuildId,
+        channels: Vec<GuildChannel>,
+    ) {
+        // The guild may have been rejoined
+        self.stopped_guild_ids.lock().await.remove(&guild_id);
+        self.start_progress(guild_id, channels.len()).await;
+
+        let mut tasks = JoinSet::new();
+        for channel in channels {
+            let backfill = self.clone();
+            let http = http.clone();
+            let cache = cache.clone();
+            tasks.spawn(async move {
+                backfill
+                    .run_channel(&http, &cache, guild_id, &channel)
+                    .await;
+            });
+        }
+        while tasks.join_next().await.is_some() {}
+
+        log::info!("Read all containers of guild {}!", guild_id);
+    }
+
+    async fn run_channel(
+        &self,
+        http: &Http,
+        cache: &Cache,
+        guild_id: GuildId,
+        channel: &GuildChannel,
+    ) {
+        self.running_channels.lock().await.insert(channel.id, false);
+        self.read_channel(http, cache, guild_id, channel).await;
+        self.running_channels.lock().await.remove(&channel.id);
+    }
+
+    async fn read_channel(
+        &self,
+        http: &Http,
+        cache: &Cache,
+        guild_id: GuildId,
+        channel: &GuildChannel,
+    ) {
+        let _permit = self
+            .semaphore
+            .acquire()
+            .await
+            .expect("The backfill semaphore is never closed!");
+
+        log::info!("Backfilling container {} ({})", channel.name, channel.id);
+        let parent_channel = get_parent_channel(cache, channel);
+        match self
+            .backfill_channel(http, guild_id, channel, parent_channel.as_ref())
+            .await
+        {
+            Ok(()) => {
+                self.update_progress(guild_id, |progress| progress.channels_done += 1)
+                    .await;
+            }
+            Err(e) => {
+                if is_missing_access(&e) {
+                    log::info!(
+                        "Skipping container {} ({}) without access",
+                        channel.name,
+                        channel.id
+                    );
+                } else if e.downcast_ref::<Stopped>().is_some() {
+                    log::info!(
+                        "Stopped backfilling removed or excluded container {} ({})",
+                        channel.name,
+                        channel.id
+                    );
+                } else {
+                    log::warn!(
+                        "Failed to backfill container {} ({}): {}",
+                        channel.name,
+                        channel.id,
+                        e
+                    );
+                }
+                self.update_progress(guild_id, |progress| progress.channels_skipped += 1)
+                    .await;
+            }
+        }
+    }
+
+    /// Reads the messages sent since the last run first and then continues
+    /// further into the history. The cursor is saved after every page.
+    async fn backfill_channel(
+        &self,
+        http: &Http,
+        guild_id: GuildId,
+        channel: &GuildChannel,
+        parent_channel: Option<&GuildChannel>,
+    ) -> Result<(), Error> {
+        let channel_id = channel.id;
+        let mut cursor = if self.reread_history {
+            BackfillCursor {
+                container_id: channel_id.to_string(),
+                oldest_entry_id: None,
+                newest_entry_id: None,
+                complete: false,
+            }
+        } else {
+            self.processor
+                .get_backfill_cursor(&channel_id.to_string())
+                .await
+                .map_err(|e| e.to_string())?
+        };
+
+        while let Some(newest_entry_id) = &cursor.newest_entry_id {
+            if self.is_stopped(guild_id, channel_id).await {
+                return Err(Stopped.into());
+            }
+
+            let get_messages = GetMessages::new()
+                .after(parse_message_id(newest_entry_id)?)
+                .limit(MESSAGE_LIMIT);
+            let messages = get_messages_with_backoff(http, channel_id, get_messages).await?;
+            let newest_message_id = match messages.iter().map(|message| message.id).max() {
+                Some(message_id) => message_id,
+                None => break,
+            };
+
+            self.add_messages(guild_id, channel, parent_channel, &messages)
+                .await?;
+            cursor.newest_entry_id = Some(newest_message_id.to_string());
+            self.save_cursor(&cursor).await?;
+        }
+
+        while !cursor.complete {
+            if self.is_stopped(guild_id, channel_id).await {
+                return Err(Stopped.into());
+            }
+
+            let mut get_messages = GetMessages::new().limit(MESSAGE_LIMIT);
+            if let Some(oldest_entry_id) = &cursor.oldest_entry_id {
+                get_messages = get_messages.before(parse_message_id(oldest_entry_id)?);
+            }
+            let messages = get_messages_with_backoff(http, channel_id, get_messages).await?;
+
+            if let Some(oldest_message_id) = messages.iter().map(|message| message.id).min() {
+                self.add_messages(guild_id, channel, parent_channel, &messages)
+                    .await?;
+                cursor.oldest_entry_id = Some(oldest_message_id.to_string());
+                if cursor.newest_entry_id.is_none() {
+                    cursor.newest_entry_id = messages
+                        .iter()
+                        .map(|message| message.id)
+                        .max()
+                        .map(|message_id| message_id.to_string());
+                }
+            } else {
+                cursor.complete = true;
+            }
+            self.save_cursor(&cursor).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds the messages unless the channel was removed or excluded while
+    /// the page was fetched.
+    async fn add_messages(
+        &self,
+        guild_id: GuildId,
+        channel: &GuildChannel,
+        parent_channel: Option<&GuildChannel>,
+        messages: &[Message],
+    ) -> Result<(), Error> {
+        let excluded_container_ids = self
+            .processor
+            .get_excluded_container_ids(&guild_id.to_string())
+            .await
+            .map_err(|e| e.to_string())?;
+        if self.is_stopped(guild_id, channel.id).await
+            || is_channel_excluded(&excluded_container_ids, channel, parent_channel)
+        {
+            return Err(Stopped.into());
+        }
+
+        let entries: Vec<Entry> = messages.iter().map(make_entry).collect();
+        self.processor
+            .add_entries(entries.as_slice())
+            .await
+            .map_err(|e| e.to_string())?;
+
+        self.update_progress(guild_id, |progress| {
+            progress.messages_read += messages.len() as u64;
+        })
+        .await;
+
+        Ok(())
+    }
+
+    async fn save_cursor(&self, cursor: &BackfillCursor) -> Result<(), Error> {
+        self.processor
+            .set_backfill_cursor(cursor)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+}
+
+fn parse_message_id(entry_id: &str) -> Result<MessageId, Error> {
+    Ok(MessageId::new(entry_id.parse()?))
+}
+
+/// Serenity waits out the rate limits it knows about. This also covers the
+/// ones it lets through, server errors and dropped connections.
+fn is_retryable(error: &serenity::Error) -> bool {
+    match error {
+        serenity::Error::Http(HttpError::UnsuccessfulRequest(response)) => {
+            response.status_code == StatusCode::TOO_MANY_REQUESTS
+                || response.status_code.is_server_error()
+        }
+        serenity::Error::Http(HttpError::Request(_)) => true,
+        _ => false,
+    }
+}
+
+fn is_missing_access(error: &Error) -> bool {
+    match error.downcast_ref::<serenity::Error>() {
+        Some(serenity::Error::Http(HttpError::UnsuccessfulRequest(response))) => {
+            response.status_code == StatusCode::FORBIDDEN
+                || MISSING_ACCESS_ERROR_CODES.contains(&response.error.code)
+        }
+        _ => false,
+    }
+}
+
+async fn get_messages_with_backoff(
+    http: &Http,
+    channel_id: ChannelId,
+    get_messages: GetMessages,
+) -> Result<Vec<Message>, Error> {
+    let mut backoff = INITIAL_BACKOFF;
+    let mut retries = 0;
+    loop {
+        match channel_id.messages(http, get_messages).await {
+            Ok(messages) => return Ok(messages),
+            Err(e) if retries < MAX_RETRIES && is_retryable(&e) => {
+                log::warn!(
+                    "Failed to get messages in container {}, retrying in {:?}: {}",
+                    channel_id,
+                    backoff,
+                    e
+                );
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+                retries += 1;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
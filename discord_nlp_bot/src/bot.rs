@@ -1,36 +1,66 @@
-use crate::commands::{ngram_by_content, ngrams_by_count, on_error, SharedCommandData};
+use crate::backfill::Backfill;
+use crate::commands::{
+    collocations, digest, distinctive, ngram_by_content, ngrams_by_count, nlp_forget_me,
+    nlp_optout, nlp_settings, nlp_status, on_error, replies, stopwords, trending,
+    SharedCommandData,
+};
 use crate::makers::make_entry;
-use nlp_bot_api::processor::entry::Entry;
-use nlp_bot_api::processor::{container, Processor};
+use crate::scheduler;
+use nlp_bot_api::processor::{container, Error as ProcessorError, Processor};
 use poise::{Framework, FrameworkOptions, PrefixFrameworkOptions};
-use serenity::all::{ChannelType, GatewayIntents, Guild, GuildChannel, Message, MessageId};
-use serenity::builder::GetMessages;
+use serenity::all::{
+    ChannelId, ChannelType, GatewayIntents, Guild, GuildChannel, LightMethod, Message, MessageId,
+    MessageUpdateEvent, PartialGuildChannel, Request, Route, ThreadsData, Timestamp,
+    UnavailableGuild,
+};
+use serenity::cache::Cache;
 use serenity::client::EventHandler;
-use serenity::http::CacheHttp;
+use serenity::http::{CacheHttp, Http};
 use serenity::model::id::GuildId;
 use serenity::prelude::Context;
 use serenity::{async_trait, Client, Error};
+use std::collections::{BTreeMap, HashSet};
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::Mutex;
 
-const MESSAGE_LIMIT: u8 = 100;
+const THREAD_LIMIT: u8 = 100;
 
-#[derive(PartialEq, Debug)]
-enum PaginationDirection {
-    /// From old messages to new ones
-    Up { message_id: Option<MessageId> },
-    /// From new messages to new ones
-    Down { message_id: MessageId },
-}
+/// Channels with messages of their own. Voice and stage channels have a text
+/// chat too.
+const MESSAGE_CHANNEL_TYPES: [ChannelType; 4] = [
+    ChannelType::Text,
+    ChannelType::News,
+    ChannelType::Voice,
+    ChannelType::Stage,
+];
+
+/// Channels which can have threads. Forum channels only have threads (posts).
+const THREAD_PARENT_CHANNEL_TYPES: [ChannelType; 3] =
+    [ChannelType::Text, ChannelType::News, ChannelType::Forum];
 
 pub async fn start(
     bot: Bot,
     processor: Arc<Processor>,
+    backfill: Arc<Backfill>,
     token: String,
 ) -> Result<(), serenity::Error> {
     let intents = GatewayIntents::non_privileged() | GatewayIntents::MESSAGE_CONTENT;
     let options = FrameworkOptions {
-        commands: vec![ngrams_by_count(), ngram_by_content()],
+        commands: vec![
+            ngrams_by_count(),
+            ngram_by_content(),
+            distinctive(),
+            collocations(),
+            trending(),
+            replies(),
+            stopwords(),
+            digest(),
+            nlp_settings(),
+            nlp_optout(),
+            nlp_forget_me(),
+            nlp_status(),
+        ],
         prefix_options: PrefixFrameworkOptions {
             prefix: Some("/nlp".into()),
             edit_tracker: Some(Arc::new(poise::EditTracker::for_timespan(
@@ -45,8 +75,10 @@ pub async fn start(
         .setup(move |ctx, _ready, framework| {
             Box::pin(async move {
                 poise::builtins::register_globally(ctx, &framework.options().commands).await?;
+                tokio::spawn(scheduler::run(ctx.http.clone(), processor.clone()));
                 Ok(SharedCommandData {
                     processor: processor.clone(),
+                    backfill: backfill.clone(),
                 })
             })
         })
@@ -61,150 +93,569 @@ pub async fn start(
     client.start().await
 }
 
+/// Channels are children of their category, or of the guild without one, and
+/// threads of the channel they were created in. A channel moved to another
+/// category is moved in the hierarchy too.
+pub async fn add_channel_container(
+    processor: &Processor,
+    channel: &GuildChannel,
+) -> Result<(), ProcessorError> {
+    let guild_container_id = channel.guild_id.to_string();
+    if let Some(category_id) = channel.parent_id.filter(|_| !is_thread(channel)) {
+        processor
+            .set_container_parent(&container::Container {
+                container_id: category_id.to_string(),
+                container_parent_id: guild_container_id.clone(),
+            })
+            .await?;
+    }
+
+    processor
+        .set_container_parent(&container::Container {
+            container_id: channel.id.to_string(),
+            container_parent_id: channel
+                .parent_id
+                .map_or(guild_container_id, |parent_id| parent_id.to_string()),
+        })
+        .await
+}
+
+/// Registers the message channels and threads which are not excluded and
+/// returns them to be read. Active threads are passed in, while archived ones
+/// are fetched for every channel which can have threads.
+pub async fn add_channels_to_read(
+    processor: &Processor,
+    http: &Http,
+    channels: &[GuildChannel],
+    threads: &[GuildChannel],
+    excluded_container_ids: &[String],
+) -> Result<Vec<GuildChannel>, ProcessorError> {
+    let mut threads: BTreeMap<ChannelId, GuildChannel> = threads
+        .iter()
+        .map(|thread| (thread.id, thread.clone()))
+        .collect();
+    let mut channels_to_read = Vec::new();
+
+    for channel in channels {
+        let is_message_channel = MESSAGE_CHANNEL_TYPES.contains(&channel.kind);
+        let is_thread_parent_channel = THREAD_PARENT_CHANNEL_TYPES.contains(&channel.kind);
+        if !is_message_channel && !is_thread_parent_channel {
+            continue;
+        }
+
+        if is_channel_excluded(excluded_container_ids, channel, None) {
+            log::info!(
+                "Skipping excluded container {} ({})",
+                channel.name,
+                channel.id
+            );
+            continue;
+        }
+
+        add_channel_container(processor, channel).await?;
+        if is_message_channel {
+            channels_to_read.push(channel.clone());
+        }
+
+        if is_thread_parent_channel {
+            for thread in get_archived_threads(http, channel).await {
+                threads.insert(thread.id, thread);
+            }
+        }
+    }
+
+    for thread in threads.values() {
+        let parent_channel = channels
+            .iter()
+            .find(|channel| thread.parent_id == Some(channel.id));
+        if is_channel_excluded(excluded_container_ids, thread, parent_channel) {
+            log::info!(
+                "Skipping excluded container {} ({})",
+                thread.name,
+                thread.id
+            );
+            continue;
+        }
+
+        add_channel_container(processor, thread).await?;
+        channels_to_read.push(thread.clone());
+    }
+
+    Ok(channels_to_read)
+}
+
+/// Returns the channel a thread was created in. Channels have none.
+pub fn get_parent_channel(cache: &Cache, channel: &GuildChannel) -> Option<GuildChannel> {
+    if !is_thread(channel) {
+        return None;
+    }
+
+    channel
+        .parent_id
+        .and_then(|parent_id| cache.channel(parent_id))
+        .map(|parent_channel| parent_channel.clone())
+}
+
 pub struct Bot {
     processor: Arc<Processor>,
+    backfill: Arc<Backfill>,
+    /// Serenity dispatches `cache_ready` again whenever a guild is added
+    /// later, so processed guilds are tracked to read each of them only once
+    processed_guild_ids: Mutex<HashSet<GuildId>>,
 }
 
 impl Bot {
-    pub fn new(processor: Arc<Processor>) -> Self {
-        Self { processor }
+    pub fn new(processor: Arc<Processor>, backfill: Arc<Backfill>) -> Self {
+        Self {
+            processor,
+            backfill,
+            processed_guild_ids: Mutex::new(HashSet::new()),
+        }
     }
 
-    async fn paginate(&self, context: &Context, channel: &GuildChannel) {
-        let first_and_last_messages_id = self
+    /// Registers the containers of the guild and starts reading their history
+    /// in the background.
+    async fn process_guild(&self, context: &Context, guild: &Guild) {
+        if !self.processed_guild_ids.lock().await.insert(guild.id) {
+            return;
+        }
+
+        self.processor
+            .add_container(&container::Container {
+                container_id: guild.id.to_string(),
+                container_parent_id: String::from("discord"),
+            })
+            .await
+            .expect("Failed to add container for guild!");
+
+        let excluded_container_ids = self
             .processor
-            .get_first_and_last_entry_id_in_container(&channel.id.to_string())
-            .await;
-
-        match first_and_last_messages_id {
-            Ok((first_message_id, last_message_id)) => {
-                self.paginate_in_direction(
-                    context,
-                    channel,
-                    PaginationDirection::Up {
-                        message_id: Some(MessageId::new(
-                            first_message_id
-                                .parse()
-                                .expect("The database contains a message ID that is not a number!"),
-                        )),
-                    },
-                )
-                .await
-                .expect("Failed to paginate up");
-
-                self.paginate_in_direction(
-                    context,
-                    channel,
-                    PaginationDirection::Down {
-                        message_id: MessageId::new(
-                            last_message_id
-                                .parse()
-                                .expect("The database contains a message ID that is not a number!"),
-                        ),
-                    },
-                )
-                .await
-                .expect("Failed to paginate down");
-            }
-            Err(_e) => {
-                self.paginate_in_direction(
-                    context,
-                    channel,
-                    PaginationDirection::Up { message_id: None },
-                )
-                .await
-                .expect("Failed to paginate up from bottom");
-            }
-        };
+            .get_excluded_container_ids(&guild.id.to_string())
+            .await
+            .expect("Failed to get excluded containers of guild!");
+
+        let channels: Vec<GuildChannel> = guild.channels.values().cloned().collect();
+        let channels_to_read = add_channels_to_read(
+            &self.processor,
+            context.http(),
+            &channels,
+            &guild.threads,
+            &excluded_container_ids,
+        )
+        .await
+        .expect("Failed to add container for channel!");
+
+        tokio::spawn(self.backfill.clone().run(
+            context.http.clone(),
+            context.cache.clone(),
+            guild.id,
+            channels_to_read,
+        ));
     }
 
-    async fn paginate_in_direction(
-        &self,
-        context: &Context,
-        channel: &GuildChannel,
-        direction: PaginationDirection,
-    ) -> Result<(), Error> {
-        log::info!(
-            "Paginating in container {} ({}) in direction {:?}",
-            channel.name,
-            channel.id,
-            direction
-        );
-
-        let mut get_messages: GetMessages = GetMessages::new().limit(MESSAGE_LIMIT);
-        get_messages = match direction {
-            PaginationDirection::Down { message_id } => get_messages.after(message_id),
-            PaginationDirection::Up { message_id } => {
-                message_id.map_or(get_messages, |message_id| get_messages.before(message_id))
+    /// Registers a channel or thread created after its guild was processed
+    /// and reads its history in the background. Channels of guilds which are
+    /// not processed yet are read along with the guild.
+    async fn process_new_channel(&self, context: &Context, channel: &GuildChannel) {
+        if !self
+            .processed_guild_ids
+            .lock()
+            .await
+            .contains(&channel.guild_id)
+        {
+            return;
+        }
+
+        let excluded_container_ids = match self
+            .processor
+            .get_excluded_container_ids(&channel.guild_id.to_string())
+            .await
+        {
+            Ok(excluded_container_ids) => excluded_container_ids,
+            Err(e) => {
+                log::warn!(
+                    "Failed to get excluded containers of guild {}: {}",
+                    channel.guild_id,
+                    e
+                );
+                return;
             }
         };
+        let parent_channel = get_parent_channel(&context.cache, channel);
+        if is_channel_excluded(&excluded_container_ids, channel, parent_channel.as_ref()) {
+            log::info!(
+                "Skipping excluded container {} ({})",
+                channel.name,
+                channel.id
+            );
+            return;
+        }
 
-        loop {
-            let messages = channel.messages(context.http(), get_messages).await?;
+        if let Err(e) = add_channel_container(&self.processor, channel).await {
+            log::warn!(
+                "Failed to add container {} ({}): {}",
+                channel.name,
+                channel.id,
+                e
+            );
+            return;
+        }
+        if is_thread(channel) || MESSAGE_CHANNEL_TYPES.contains(&channel.kind) {
+            tokio::spawn(self.backfill.clone().run(
+                context.http.clone(),
+                context.cache.clone(),
+                channel.guild_id,
+                vec![channel.clone()],
+            ));
+        }
+    }
 
-            let last_message_id = match messages.last() {
-                Some(message) => message.id,
-                None => break,
-            };
+    /// Removes a deleted channel, thread or guild from the container
+    /// hierarchy. Its stored entries and children are kept.
+    async fn remove_container(&self, container_id: &str) {
+        match self.processor.remove_container(container_id).await {
+            Ok(()) => log::info!("Removed container {}", container_id),
+            Err(e) => log::warn!("Failed to remove container {}: {}", container_id, e),
+        }
+    }
 
-            let entries: Vec<Entry> = messages.iter().map(make_entry).collect();
-            self.processor
-                .add_entries(entries.as_slice())
-                .await
-                .expect("Failed to add entries!");
+    async fn is_message_excluded(&self, context: &Context, message: &Message) -> bool {
+        let guild_id = match message.guild_id {
+            Some(guild_id) => guild_id,
+            None => return false,
+        };
 
-            get_messages = match direction {
-                PaginationDirection::Up { message_id: _id } => get_messages.before(last_message_id),
-                PaginationDirection::Down { message_id: _id } => {
-                    get_messages.after(last_message_id)
-                }
-            };
+        let excluded_container_ids = self
+            .processor
+            .get_excluded_container_ids(&guild_id.to_string())
+            .await
+            .expect("Failed to get excluded containers of guild!");
+        if excluded_container_ids.is_empty() {
+            return false;
         }
 
-        Ok(())
+        // Without the channel in the cache, its category is unknown
+        let channels = context.cache.guild(guild_id).map(|guild| {
+            let channel = guild
+                .channels
+                .get(&message.channel_id)
+                .cloned()
+                .or_else(|| {
+                    guild
+                        .threads
+                        .iter()
+                        .find(|thread| thread.id == message.channel_id)
+                        .cloned()
+                });
+            let parent_channel = channel
+                .as_ref()
+                .filter(|channel| is_thread(channel))
+                .and_then(|channel| channel.parent_id)
+                .and_then(|parent_id| guild.channels.get(&parent_id).cloned());
+
+            (channel, parent_channel)
+        });
+        match channels {
+            Some((Some(channel), parent_channel)) => {
+                is_channel_excluded(&excluded_container_ids, &channel, parent_channel.as_ref())
+            }
+            _ => excluded_container_ids.contains(&message.channel_id.to_string()),
+        }
     }
+}
 
-    async fn process_channel(&self, context: &Context, channel: &GuildChannel) {
-        self.processor
-            .add_container(&container::Container {
-                container_id: channel.id.to_string(),
-                container_parent_id: channel.guild_id.to_string(),
-            })
-            .await
-            .expect("Failed to add container for channel!");
+pub const fn is_thread(channel: &GuildChannel) -> bool {
+    matches!(
+        channel.kind,
+        ChannelType::PublicThread | ChannelType::PrivateThread | ChannelType::NewsThread
+    )
+}
+
+/// Channels are excluded directly or through their category. Threads are
+/// excluded through their parent channel and its category as well.
+pub fn is_channel_excluded(
+    excluded_container_ids: &[String],
+    channel: &GuildChannel,
+    parent_channel: Option<&GuildChannel>,
+) -> bool {
+    [
+        Some(channel.id),
+        channel.parent_id,
+        parent_channel.and_then(|parent_channel| parent_channel.parent_id),
+    ]
+    .iter()
+    .flatten()
+    .any(|id| excluded_container_ids.contains(&id.to_string()))
+}
 
-        self.paginate(context, channel).await;
+/// Serenity passes `before` as a number, while Discord expects an ISO 8601
+/// timestamp, so the request is built here.
+async fn get_archived_public_threads(
+    http: &Http,
+    channel_id: ChannelId,
+    before: Option<Timestamp>,
+) -> Result<ThreadsData, Error> {
+    let mut params = vec![("limit", THREAD_LIMIT.to_string())];
+    if let Some(before) = before {
+        params.push(("before", before.to_string()));
     }
 
-    async fn process_guild(&self, context: &Context, guild: &Guild) {
-        self.processor
-            .add_container(&container::Container {
-                container_id: guild.id.to_string(),
-                container_parent_id: String::from("discord"),
-            })
-            .await
-            .expect("Failed to add container for guild!");
+    http.fire(
+        Request::new(
+            Route::ChannelArchivedPublicThreads { channel_id },
+            LightMethod::Get,
+        )
+        .params(Some(params)),
+    )
+    .await
+}
 
-        for channel in guild.channels.values() {
-            if channel.kind == ChannelType::Text {
-                self.process_channel(context, channel).await;
+/// Private archived threads need the Manage Threads permission, so only the
+/// public ones are returned. Threads which fail to load are logged and
+/// skipped.
+async fn get_archived_threads(http: &Http, channel: &GuildChannel) -> Vec<GuildChannel> {
+    let mut threads = Vec::new();
+    let mut before = None;
+    loop {
+        let threads_data = match get_archived_public_threads(http, channel.id, before).await {
+            Ok(threads_data) => threads_data,
+            Err(e) => {
+                log::warn!(
+                    "Failed to get archived threads of container {} ({}): {}",
+                    channel.name,
+                    channel.id,
+                    e
+                );
+                break;
             }
+        };
+
+        // Threads are returned from the most recently archived
+        before = threads_data
+            .threads
+            .last()
+            .and_then(|thread| thread.thread_metadata)
+            .and_then(|thread_metadata| thread_metadata.archive_timestamp);
+        threads.extend(threads_data.threads);
+
+        if !threads_data.has_more || before.is_none() {
+            break;
         }
     }
+
+    threads
 }
 
 #[async_trait]
 impl EventHandler for Bot {
-    // TODO: Handle updates
-    // TODO: Model relations (replies)
+    async fn message(&self, context: Context, new_message: Message) {
+        if self.is_message_excluded(&context, &new_message).await {
+            return;
+        }
 
-    async fn message(&self, _context: Context, new_message: Message) {
         self.processor
             .add_entry(make_entry(&new_message))
             .await
             .expect("Failed to add entry!");
     }
 
+    async fn message_update(
+        &self,
+        context: Context,
+        _old_if_available: Option<Message>,
+        new: Option<Message>,
+        event: MessageUpdateEvent,
+    ) {
+        // Updates without content are e.g. embeds being resolved
+        if event.content.is_none() {
+            return;
+        }
+
+        let message = match new {
+            Some(message) => message,
+            None => match event.channel_id.message(&context, event.id).await {
+                Ok(message) => message,
+                Err(e) => {
+                    log::warn!("Failed to get updated message {}: {}", event.id, e);
+                    return;
+                }
+            },
+        };
+
+        if self.is_message_excluded(&context, &message).await {
+            return;
+        }
+
+        self.processor
+            .update_entry(make_entry(&message))
+            .await
+            .expect("Failed to update entry!");
+    }
+
+    async fn message_delete(
+        &self,
+        _context: Context,
+        _channel_id: ChannelId,
+        deleted_message_id: MessageId,
+        _guild_id: Option<GuildId>,
+    ) {
+        self.processor
+            .delete_entry(&deleted_message_id.to_string())
+            .await
+            .expect("Failed to delete entry!");
+    }
+
+    async fn message_delete_bulk(
+        &self,
+        _context: Context,
+        _channel_id: ChannelId,
+        multiple_deleted_messages_ids: Vec<MessageId>,
+        _guild_id: Option<GuildId>,
+    ) {
+        let entry_ids: Vec<String> = multiple_deleted_messages_ids
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        self.processor
+            .delete_entries(entry_ids.as_slice())
+            .await
+            .expect("Failed to delete entries!");
+    }
+
+    async fn guild_create(&self, context: Context, guild: Guild, is_new: Option<bool>) {
+        // Guilds available at startup are processed once the cache is ready
+        if is_new == Some(true) {
+            self.process_guild(&context, &guild).await;
+        }
+    }
+
+    async fn guild_delete(
+        &self,
+        _context: Context,
+        incomplete: UnavailableGuild,
+        _full: Option<Guild>,
+    ) {
+        // An unavailable guild is in an outage, the bot was not removed from it
+        if incomplete.unavailable {
+            return;
+        }
+
+        self.processed_guild_ids.lock().await.remove(&incomplete.id);
+        self.backfill.stop_guild(incomplete.id).await;
+
+        let container_id = incomplete.id.to_string();
+        self.remove_container(&container_id).await;
+        if let Err(e) = self.processor.remove_digest_schedule(&container_id).await {
+            log::warn!(
+                "Failed to remove digest schedule of guild {}: {}",
+                container_id,
+                e
+            );
+        }
+    }
+
+    async fn channel_create(&self, context: Context, channel: GuildChannel) {
+        if !MESSAGE_CHANNEL_TYPES.contains(&channel.kind)
+            && !THREAD_PARENT_CHANNEL_TYPES.contains(&channel.kind)
+        {
+            return;
+        }
+
+        self.process_new_channel(&context, &channel).await;
+    }
+
+    async fn channel_update(
+        &self,
+        _context: Context,
+        _old: Option<GuildChannel>,
+        new: GuildChannel,
+    ) {
+        if !MESSAGE_CHANNEL_TYPES.contains(&new.kind)
+            && !THREAD_PARENT_CHANNEL_TYPES.contains(&new.kind)
+        {
+            return;
+        }
+
+        if !self
+            .processed_guild_ids
+            .lock()
+            .await
+            .contains(&new.guild_id)
+        {
+            return;
+        }
+
+        // The category of the channel may have changed
+        if let Err(e) = add_channel_container(&self.processor, &new).await {
+            log::warn!("Failed to move container {} ({}): {}", new.name, new.id, e);
+        }
+    }
+
+    async fn channel_delete(
+        &self,
+        _context: Context,
+        channel: GuildChannel,
+        _messages: Option<Vec<Message>>,
+    ) {
+        // Discord moves the channels of a deleted category to the guild and
+        // they are moved in `channel_update`. Until then, the category keeps
+        // them in the guild.
+        if channel.kind == ChannelType::Category {
+            return;
+        }
+
+        self.backfill.stop_channel(channel.id).await;
+        self.remove_container(&channel.id.to_string()).await;
+
+        // The digest can't be posted to a deleted channel anymore
+        let guild_container_id = channel.guild_id.to_string();
+        let schedule = match self
+            .processor
+            .get_digest_schedule(&guild_container_id)
+            .await
+        {
+            Ok(schedule) => schedule,
+            Err(e) => {
+                log::warn!(
+                    "Failed to get digest schedule of guild {}: {}",
+                    guild_container_id,
+                    e
+                );
+                return;
+            }
+        };
+        if schedule.map_or(false, |schedule| {
+            schedule.channel_id == channel.id.to_string()
+        }) {
+            if let Err(e) = self
+                .processor
+                .remove_digest_schedule(&guild_container_id)
+                .await
+            {
+                log::warn!(
+                    "Failed to remove digest schedule of guild {}: {}",
+                    guild_container_id,
+                    e
+                );
+            }
+        }
+    }
+
+    async fn thread_create(&self, context: Context, thread: GuildChannel) {
+        self.process_new_channel(&context, &thread).await;
+    }
+
+    async fn thread_delete(
+        &self,
+        _context: Context,
+        thread: PartialGuildChannel,
+        _full_thread_data: Option<GuildChannel>,
+    ) {
+        self.backfill.stop_channel(thread.id).await;
+        self.remove_container(&thread.id.to_string()).await;
+    }
+
     async fn cache_ready(&self, context: Context, guilds: Vec<GuildId>) {
         log::info!("Discord cache is ready...");
         for guild_id in guilds {
@@ -218,6 +669,6 @@ impl EventHandler for Bot {
             self.process_guild(&context, &guild).await;
         }
 
-        log::info!("Read all containers!");
+        log::info!("Registered all containers!");
     }
 }
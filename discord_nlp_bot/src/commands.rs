@@ -1,18 +1,36 @@
-use nlp_bot_api::displayers::chart::display_ngram_count_over_time;
+use nlp_bot_api::displayers::{chart::display_ngram_count_over_time, locale::Locale};
 use nlp_bot_api::{
-    displayers::ascii_table::display_ngram_list, processor::Processor, store::filters::Order,
+    displayers::ascii_table::{
+        display_collocation_list, display_distinctive_ngram_list, display_ngram_list,
+        display_reply_pair_list, display_trending_ngram_list,
+    },
+    processor::{
+        digest::DigestSchedule,
+        ngram::{Granularity, NgramSeriesForByContentCommand, MAX_NGRAM_LENGTH},
+        settings::GuildSettings,
+        stopwords::Language,
+        Processor, DEFAULT_COLLOCATION_MIN_COUNT, DEFAULT_TRENDING_BASELINE_WEEKS,
+        DEFAULT_TRENDING_WEEKS,
+    },
+    store::filters::Order,
 };
 use poise::CreateReply;
-use serenity::all::Member;
-use serenity::builder::CreateAttachment;
+use serenity::all::{ChannelType, GuildChannel, Member};
+use serenity::builder::{CreateAllowedMentions, CreateAttachment};
 use std::cmp::Ordering;
 use std::str::FromStr;
 use std::sync::Arc;
 
+use crate::backfill::{Backfill, GuildProgress};
+use crate::bot::{add_channels_to_read, is_thread};
 use crate::message_formatters::format_table;
+use crate::scheduler::{create_digest_message, get_next_run_time, parse_schedule};
+
+pub const MAX_NGRAMS_IN_CHART: usize = 10;
 
 pub struct SharedCommandData {
     pub processor: Arc<Processor>,
+    pub backfill: Arc<Backfill>,
 }
 
 type Error = Box<dyn std::error::Error + Send + Sync>;
@@ -45,10 +63,10 @@ async fn send_error_message(context: &Context<'_>, error: &str) -> Result<(), Er
 
 fn get_container_ids_from_context(
     context: &Context<'_>,
-    container_context: &Option<String>,
+    container_context: Option<&String>,
 ) -> Result<Vec<String>, String> {
     let container_ids: Vec<String>;
-    match container_context.as_deref().unwrap_or("server") {
+    match container_context.map_or("server", String::as_str) {
         "channel" => container_ids = vec![context.channel_id().to_string()],
         "server" => match context.guild_id() {
             Some(guild_id) => container_ids = vec![guild_id.to_string()],
@@ -62,29 +80,144 @@ fn get_container_ids_from_context(
                     .into(),
             );
         }
-    };
+    }
 
     Ok(container_ids)
 }
 
-fn get_options_text(mut options: Vec<Option<(&str, String, bool)>>) -> String {
-    options.sort();
-    let filtered_options: Vec<(&str, String, bool)> =
-        options.iter().filter_map(|o| o.as_ref().cloned()).collect();
+/// Returns the container IDs of the context one level above the given one
+fn get_parent_container_ids_from_context(
+    context: &Context<'_>,
+    container_context: &str,
+) -> Result<Vec<String>, String> {
+    match container_context {
+        "channel" => context
+            .guild_id()
+            .map(|guild_id| vec![guild_id.to_string()])
+            .ok_or_else(|| "You can't compare a DM to its server!".into()),
+        "server" => Ok(vec!["discord".to_string()]),
+        _ => Err("Only the `channel` and `server` contexts can be compared to their parent".into()),
+    }
+}
+
+fn get_guild_container_id(context: &Context<'_>) -> Result<String, String> {
+    context
+        .guild_id()
+        .map(|guild_id| guild_id.to_string())
+        .ok_or_else(|| "You can't use this command in a DM!".into())
+}
+
+/// Returns the settings of the guild, or the defaults in a DM
+async fn get_guild_settings(context: &Context<'_>) -> Result<GuildSettings, String> {
+    match context.guild_id() {
+        Some(guild_id) => context
+            .data()
+            .processor
+            .get_guild_settings(&guild_id.to_string())
+            .await
+            .map_err(|e| e.to_string()),
+        None => Ok(GuildSettings::default()),
+    }
+}
+
+/// Invalid locales are ignored, so that a chart is drawn anyway
+pub fn get_locale(settings: &GuildSettings) -> Option<Locale> {
+    settings
+        .locale
+        .as_deref()
+        .and_then(|locale| Locale::from_str(locale).ok())
+}
+
+fn get_granularity_from_option(
+    granularity_option: Option<&String>,
+) -> Result<Option<Granularity>, String> {
+    granularity_option
+        .map(|granularity| {
+            Granularity::from_str(granularity).map_err(|_| {
+                "The granularity you specified was neither `hour`, `day`, `week` nor `month`".into()
+            })
+        })
+        .transpose()
+}
+
+fn check_ngram_length(length: Option<u32>) -> Result<(), String> {
+    match length {
+        Some(length) if length < 1 || length > u32::from(MAX_NGRAM_LENGTH) => Err(format!(
+            "The length of the n-grams must be between 1 and {}!",
+            MAX_NGRAM_LENGTH
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn check_stopwords_option(stopwords_option: &str) -> Result<(), String> {
+    if stopwords_option == "custom"
+        || stopwords_option == "none"
+        || Language::from_str(stopwords_option).is_ok()
+    {
+        return Ok(());
+    }
+
+    Err(
+        "The stop-words you specified were neither `en`, `cs`, `es`, `de`, `custom` nor `none`"
+            .into(),
+    )
+}
+
+/// Returns the stop-words for the `stopwords` option, which is either a
+/// language, `custom` for only the server's custom stop-words or `none`
+async fn get_stopwords_from_option(
+    context: &Context<'_>,
+    stopwords_option: Option<&String>,
+) -> Result<Vec<String>, String> {
+    let stopwords_option = match stopwords_option {
+        None => return Ok(Vec::new()),
+        Some(stopwords_option) => stopwords_option,
+    };
+    check_stopwords_option(stopwords_option)?;
+    if stopwords_option == "none" {
+        return Ok(Vec::new());
+    }
+    let language = Language::from_str(stopwords_option).ok();
+    let guild_id = context.guild_id().map(|guild_id| guild_id.to_string());
 
+    context
+        .data()
+        .processor
+        .get_stopwords(language, guild_id.as_deref())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Splits the words by commas and whitespace
+fn split_words(words_string: &str) -> Vec<String> {
+    words_string
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+pub fn split_ngram_contents(ngram_contents_string: &str) -> Vec<String> {
+    ngram_contents_string
+        .split(',')
+        .map(str::trim)
+        .filter(|ngram_content| !ngram_content.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Joins the items as "a, b and c"
+fn get_list_text(items: &[String]) -> String {
     let mut text = String::new();
-    for (index, (name, description, code_block)) in filtered_options.iter().enumerate() {
-        if *code_block {
-            text += &format!("{} `{}`", name, description);
-        } else {
-            text += &format!("{} {}", name, description);
-        }
+    for (index, item) in items.iter().enumerate() {
+        text += item;
 
-        if filtered_options.len() < 2 {
+        if items.len() < 2 {
             continue;
         }
 
-        match index.cmp(&(filtered_options.len() - 2)) {
+        match index.cmp(&(items.len() - 2)) {
             Ordering::Less => text += ", ",
             Ordering::Equal => text += " and ",
             Ordering::Greater => (),
@@ -93,6 +226,23 @@ fn get_options_text(mut options: Vec<Option<(&str, String, bool)>>) -> String {
     text
 }
 
+fn get_options_text(mut options: Vec<Option<(&str, String, bool)>>) -> String {
+    options.sort();
+    let option_texts: Vec<String> = options
+        .iter()
+        .flatten()
+        .map(|(name, description, code_block)| {
+            if *code_block {
+                format!("{} `{}`", name, description)
+            } else {
+                format!("{} {}", name, description)
+            }
+        })
+        .collect();
+
+    get_list_text(&option_texts)
+}
+
 #[poise::command(
     prefix_command,
     slash_command,
@@ -110,10 +260,18 @@ pub async fn ngrams_by_count(
     #[description = "The way to order n-grams by occurrence count. Either `asc` or `desc`."]
     #[rename = "order"]
     order_string: Option<String>,
+    #[description = "Exclude stop-words of this language and the server's custom ones. Either `en`, `cs`, `es`, `de`, `custom` or `none`."]
+    #[rename = "stopwords"]
+    stopwords_option: Option<String>,
 ) -> Result<(), Error> {
     // This command can take some time
     if let Err(error) = context.defer().await {
         return send_error_message(&context, &error.to_string()).await;
+    }
+
+    let settings = match get_guild_settings(&context).await {
+        Ok(settings) => settings,
+        Err(error) => return send_error_message(&context, &error).await,
     };
 
     let mut order: Option<Order> = None;
@@ -129,17 +287,27 @@ pub async fn ngrams_by_count(
             }
         }
     }
-    let container_ids = match get_container_ids_from_context(&context, &container_context) {
+    let container_ids = match get_container_ids_from_context(
+        &context,
+        container_context
+            .as_ref()
+            .or(settings.default_context.as_ref()),
+    ) {
         Ok(container_ids) => container_ids,
         Err(error) => return send_error_message(&context, &error).await,
     };
-    if length.is_some() && length < Some(1) || length > Some(5) {
-        return send_error_message(
-            &context,
-            "The length of the n-grams must be between 1 and 5!",
-        )
-        .await;
+    if let Err(error) = check_ngram_length(length) {
+        return send_error_message(&context, &error).await;
     }
+    let stopwords = match get_stopwords_from_option(
+        &context,
+        stopwords_option.as_ref().or(settings.stopwords.as_ref()),
+    )
+    .await
+    {
+        Ok(stopwords) => stopwords,
+        Err(error) => return send_error_message(&context, &error).await,
+    };
 
     let ngrams_result = context
         .data()
@@ -148,9 +316,10 @@ pub async fn ngrams_by_count(
         .get_ngrams_by_count(
             sender.clone().map(|sender| sender.user.to_string()),
             length,
-            amount,
+            amount.or(settings.default_limit),
             &container_ids,
             order.clone(),
+            &stopwords,
         )
         .await;
 
@@ -171,7 +340,8 @@ pub async fn ngrams_by_count(
         get_options_text(vec![
             sender.map(|s| ("sender", s.user.to_string(), false)),
             container_context.map(|c| ("context", c, true)),
-            order_string.map(|o| ("order", o, true))
+            order_string.map(|o| ("order", o, true)),
+            stopwords_option.map(|s| ("stop-words", s, true)),
         ])
     );
 
@@ -190,56 +360,114 @@ pub async fn ngrams_by_count(
 )]
 pub async fn ngram_by_content(
     context: Context<'_>,
-    #[rename = "ngram"]
-    #[description = "The ngram about which to get data."]
-    ngram_content: String,
+    #[rename = "ngrams"]
+    #[description = "The n-grams about which to get data, separated by commas."]
+    ngram_contents_string: String,
     #[rename = "sender"]
     #[description = "Look for n-grams sent by this user."]
     sender: Option<Member>,
     #[rename = "context"]
     #[description = "Look for n-grams sent in this context. Either `channel`, `server`, `discord` or `all`."]
     container_context: Option<String>,
+    #[rename = "granularity"]
+    #[description = "The size of the time buckets. Either `hour`, `day`, `week` or `month`."]
+    granularity_string: Option<String>,
+    #[description = "Show the occurrences per 1,000 tokens instead of the number of occurrences."]
+    normalise: Option<bool>,
 ) -> Result<(), Error> {
     // This command can take some time
     if let Err(error) = context.defer().await {
         return send_error_message(&context, &error.to_string()).await;
+    }
+
+    let settings = match get_guild_settings(&context).await {
+        Ok(settings) => settings,
+        Err(error) => return send_error_message(&context, &error).await,
+    };
+
+    let granularity = match get_granularity_from_option(granularity_string.as_ref()) {
+        Ok(granularity) => granularity,
+        Err(error) => return send_error_message(&context, &error).await,
     };
 
+    let ngram_contents = split_ngram_contents(&ngram_contents_string);
+    if ngram_contents.is_empty() || ngram_contents.len() > MAX_NGRAMS_IN_CHART {
+        return send_error_message(
+            &context,
+            &format!(
+                "You must specify between 1 and {} n-grams!",
+                MAX_NGRAMS_IN_CHART
+            ),
+        )
+        .await;
+    }
+
+    let normalise = normalise.unwrap_or(false);
     let processor = context.data().processor.clone();
-    let container_ids = match get_container_ids_from_context(&context, &container_context) {
+    let container_ids = match get_container_ids_from_context(
+        &context,
+        container_context
+            .as_ref()
+            .or(settings.default_context.as_ref()),
+    ) {
         Ok(container_ids) => container_ids,
         Err(error) => return send_error_message(&context, &error).await,
     };
 
-    let ngrams_result = processor
-        .get_ngram_by_content(
-            &ngram_content,
+    let ngram_series_result = processor
+        .get_ngrams_by_content(
+            &ngram_contents,
             sender.clone().map(|sender| sender.user.to_string()),
             &container_ids,
+            granularity,
+            normalise,
         )
         .await;
-    let ngrams = match ngrams_result {
-        Ok(ngrams) => ngrams,
+    let ngram_series: Vec<NgramSeriesForByContentCommand> = match ngram_series_result {
+        Ok(ngram_series) => ngram_series
+            .into_iter()
+            .filter(|series| !series.ngrams.is_empty())
+            .collect(),
         Err(e) => {
             return send_error_message(&context, &e.to_string()).await;
         }
     };
-    if ngrams.is_empty() {
+    if ngram_series.is_empty() {
         context.say("No n-grams found!").await?;
         return Ok(());
     }
-    let image = match display_ngram_count_over_time(&ngrams) {
+    let image = match display_ngram_count_over_time(
+        &ngram_series,
+        granularity.unwrap_or_default(),
+        normalise,
+        get_locale(&settings),
+    ) {
         Ok(image) => image,
         Err(e) => {
             return send_error_message(&context, &e.to_string()).await;
         }
     };
+    let found_ngram_contents: Vec<String> = ngram_series
+        .iter()
+        .map(|series| format!("`{}`", series.content))
+        .collect();
     let message = format!(
-        "Here's a chart of the number of occurrences of the n-gram `{}` over time with {}",
-        ngram_content,
+        "Here's a chart of the {} of the n-gram{} {} over time with {}",
+        if normalise {
+            "occurrences per 1,000 tokens"
+        } else {
+            "number of occurrences"
+        },
+        if found_ngram_contents.len() > 1 {
+            "s"
+        } else {
+            ""
+        },
+        get_list_text(&found_ngram_contents),
         get_options_text(vec![
             sender.map(|s| ("sender", s.user.to_string(), false)),
             container_context.map(|c| ("context", c, true)),
+            granularity_string.map(|g| ("granularity", g, true)),
         ])
     );
 
@@ -253,3 +481,1240 @@ pub async fn ngram_by_content(
 
     Ok(())
 }
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    track_edits,
+    required_permissions = "SEND_MESSAGES"
+)]
+pub async fn distinctive(
+    context: Context<'_>,
+    #[description = "Compare the n-grams sent by this user to the ones sent by everyone in the context."]
+    sender: Option<Member>,
+    #[description = "Length of the n-grams to look for."] length: Option<u32>,
+    #[description = "The amount of n-grams to get."] amount: Option<u32>,
+    #[description = "Without a sender, compare this context to its parent. Either `channel` or `server`."]
+    #[rename = "context"]
+    container_context: Option<String>,
+) -> Result<(), Error> {
+    // This command can take some time
+    if let Err(error) = context.defer().await {
+        return send_error_message(&context, &error.to_string()).await;
+    }
+
+    let settings = match get_guild_settings(&context).await {
+        Ok(settings) => settings,
+        Err(error) => return send_error_message(&context, &error).await,
+    };
+
+    if let Err(error) = check_ngram_length(length) {
+        return send_error_message(&context, &error).await;
+    }
+
+    // The context is compared to its parent or to itself, so `discord` and
+    // `all` make no sense here and the default is chosen by the sender
+    let default_container_context = settings
+        .default_context
+        .clone()
+        .filter(|default_context| default_context == "channel" || default_context == "server")
+        .unwrap_or_else(|| {
+            if sender.is_some() {
+                String::from("server")
+            } else {
+                String::from("channel")
+            }
+        });
+    let container_context_or_default = container_context
+        .clone()
+        .unwrap_or(default_container_context);
+    let container_ids =
+        match get_container_ids_from_context(&context, Some(&container_context_or_default)) {
+            Ok(container_ids) => container_ids,
+            Err(error) => return send_error_message(&context, &error).await,
+        };
+    let background_container_ids = if sender.is_some() {
+        container_ids.clone()
+    } else {
+        match get_parent_container_ids_from_context(&context, &container_context_or_default) {
+            Ok(container_ids) => container_ids,
+            Err(error) => return send_error_message(&context, &error).await,
+        }
+    };
+
+    let ngrams_result = context
+        .data()
+        .processor
+        .get_distinctive_ngrams(
+            sender.clone().map(|sender| sender.user.to_string()),
+            length,
+            amount.or(settings.default_limit),
+            &container_ids,
+            &background_container_ids,
+        )
+        .await;
+    let ngrams = match ngrams_result {
+        Ok(ngrams) => ngrams,
+        Err(e) => {
+            return send_error_message(&context, &e.to_string()).await;
+        }
+    };
+
+    if ngrams.is_empty() {
+        context.say("No distinctive n-grams found!").await?;
+        return Ok(());
+    }
+
+    let heading = format!(
+        "Here's a table of distinctive n-grams by log-likelihood score with {}",
+        get_options_text(vec![
+            sender.map(|s| ("sender", s.user.to_string(), false)),
+            Some(("context", container_context_or_default, true)),
+        ])
+    );
+
+    let ngrams_table = display_distinctive_ngram_list(ngrams.as_slice());
+    let ngrams_message_content = format_table(&ngrams_table, &heading);
+    context.say(ngrams_message_content).await?;
+
+    Ok(())
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    track_edits,
+    required_permissions = "SEND_MESSAGES"
+)]
+pub async fn collocations(
+    context: Context<'_>,
+    #[description = "Length of the n-grams to look for. Either 2 or 3."] length: Option<u32>,
+    #[description = "Only consider n-grams occurring at least this many times."]
+    #[rename = "minimum"]
+    min_count: Option<u32>,
+    #[description = "The amount of n-grams to get."] amount: Option<u32>,
+    #[description = "Look for n-grams sent in this context. Either `channel`, `server`, `discord` or `all`."]
+    #[rename = "context"]
+    container_context: Option<String>,
+) -> Result<(), Error> {
+    // This command can take some time
+    if let Err(error) = context.defer().await {
+        return send_error_message(&context, &error.to_string()).await;
+    }
+
+    let settings = match get_guild_settings(&context).await {
+        Ok(settings) => settings,
+        Err(error) => return send_error_message(&context, &error).await,
+    };
+
+    if length.is_some() && length != Some(2) && length != Some(3) {
+        return send_error_message(&context, "The length of the n-grams must be 2 or 3!").await;
+    }
+    let container_ids = match get_container_ids_from_context(
+        &context,
+        container_context
+            .as_ref()
+            .or(settings.default_context.as_ref()),
+    ) {
+        Ok(container_ids) => container_ids,
+        Err(error) => return send_error_message(&context, &error).await,
+    };
+
+    let ngrams_result = context
+        .data()
+        .processor
+        .get_collocations(
+            length,
+            min_count,
+            amount.or(settings.default_limit),
+            &container_ids,
+        )
+        .await;
+    let ngrams = match ngrams_result {
+        Ok(ngrams) => ngrams,
+        Err(e) => {
+            return send_error_message(&context, &e.to_string()).await;
+        }
+    };
+
+    if ngrams.is_empty() {
+        context.say("No collocations found!").await?;
+        return Ok(());
+    }
+
+    let heading = format!(
+        "Here's a table of collocations by pointwise mutual information with {}",
+        get_options_text(vec![
+            Some((
+                "minimum count",
+                min_count
+                    .unwrap_or(DEFAULT_COLLOCATION_MIN_COUNT)
+                    .to_string(),
+                false
+            )),
+            container_context.map(|c| ("context", c, true)),
+        ])
+    );
+
+    let ngrams_table = display_collocation_list(ngrams.as_slice());
+    let ngrams_message_content = format_table(&ngrams_table, &heading);
+    context.say(ngrams_message_content).await?;
+
+    Ok(())
+}
+
+fn get_weeks_text(weeks: u32) -> String {
+    if weeks == 1 {
+        return String::from("week");
+    }
+
+    format!("{} weeks", weeks)
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    track_edits,
+    required_permissions = "SEND_MESSAGES"
+)]
+pub async fn trending(
+    context: Context<'_>,
+    #[description = "Length of the n-grams to look for."] length: Option<u32>,
+    #[description = "The amount of n-grams to get."] amount: Option<u32>,
+    #[description = "Look for n-grams sent in this context. Either `channel`, `server`, `discord` or `all`."]
+    #[rename = "context"]
+    container_context: Option<String>,
+    #[description = "The number of recent weeks to look at."] weeks: Option<u32>,
+    #[description = "The number of weeks before the recent ones to compare to."]
+    #[rename = "baseline"]
+    baseline_weeks: Option<u32>,
+    #[description = "Only consider n-grams occurring at least this many times recently."]
+    #[rename = "minimum"]
+    min_count: Option<u32>,
+) -> Result<(), Error> {
+    // This command can take some time
+    if let Err(error) = context.defer().await {
+        return send_error_message(&context, &error.to_string()).await;
+    }
+
+    let settings = match get_guild_settings(&context).await {
+        Ok(settings) => settings,
+        Err(error) => return send_error_message(&context, &error).await,
+    };
+
+    if let Err(error) = check_ngram_length(length) {
+        return send_error_message(&context, &error).await;
+    }
+    let container_ids = match get_container_ids_from_context(
+        &context,
+        container_context
+            .as_ref()
+            .or(settings.default_context.as_ref()),
+    ) {
+        Ok(container_ids) => container_ids,
+        Err(error) => return send_error_message(&context, &error).await,
+    };
+
+    let ngrams_result = context
+        .data()
+        .processor
+        .get_trending_ngrams(
+            length,
+            amount.or(settings.default_limit),
+            &container_ids,
+            weeks,
+            baseline_weeks,
+            min_count,
+        )
+        .await;
+    let ngrams = match ngrams_result {
+        Ok(ngrams) => ngrams,
+        Err(e) => {
+            return send_error_message(&context, &e.to_string()).await;
+        }
+    };
+
+    if ngrams.is_empty() {
+        context.say("No trending n-grams found!").await?;
+        return Ok(());
+    }
+
+    // The processor looks at least one week back
+    let heading = format!(
+        "Here's a table of n-grams trending in the last {} compared to the {} before with {}",
+        get_weeks_text(weeks.unwrap_or(DEFAULT_TRENDING_WEEKS).max(1)),
+        get_weeks_text(
+            baseline_weeks
+                .unwrap_or(DEFAULT_TRENDING_BASELINE_WEEKS)
+                .max(1)
+        ),
+        get_options_text(vec![container_context.map(|c| ("context", c, true))])
+    );
+
+    let ngrams_table = display_trending_ngram_list(ngrams.as_slice());
+    let ngrams_message_content = format_table(&ngrams_table, &heading);
+    context.say(ngrams_message_content).await?;
+
+    Ok(())
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    track_edits,
+    required_permissions = "SEND_MESSAGES"
+)]
+pub async fn replies(
+    context: Context<'_>,
+    #[description = "The amount of sender pairs to get."] amount: Option<u32>,
+    #[description = "Look for replies sent in this context. Either `channel`, `server`, `discord` or `all`."]
+    #[rename = "context"]
+    container_context: Option<String>,
+) -> Result<(), Error> {
+    // This command can take some time
+    if let Err(error) = context.defer().await {
+        return send_error_message(&context, &error.to_string()).await;
+    }
+
+    let settings = match get_guild_settings(&context).await {
+        Ok(settings) => settings,
+        Err(error) => return send_error_message(&context, &error).await,
+    };
+
+    let container_ids = match get_container_ids_from_context(
+        &context,
+        container_context
+            .as_ref()
+            .or(settings.default_context.as_ref()),
+    ) {
+        Ok(container_ids) => container_ids,
+        Err(error) => return send_error_message(&context, &error).await,
+    };
+
+    let reply_pairs_result = context
+        .data()
+        .processor
+        .get_reply_pairs(amount.or(settings.default_limit), &container_ids)
+        .await;
+    let reply_pairs = match reply_pairs_result {
+        Ok(reply_pairs) => reply_pairs,
+        Err(e) => {
+            return send_error_message(&context, &e.to_string()).await;
+        }
+    };
+
+    if reply_pairs.is_empty() {
+        context.say("No replies found!").await?;
+        return Ok(());
+    }
+
+    let heading = format!(
+        "Here's a table of who replies to whom most often with {}",
+        get_options_text(vec![container_context.map(|c| ("context", c, true))])
+    );
+
+    let reply_pairs_table = display_reply_pair_list(reply_pairs.as_slice());
+    let reply_pairs_message_content = format_table(&reply_pairs_table, &heading);
+    context.say(reply_pairs_message_content).await?;
+
+    Ok(())
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    subcommands("stopwords_add", "stopwords_remove", "stopwords_list"),
+    subcommand_required
+)]
+#[allow(clippy::unused_async)]
+pub async fn stopwords(_context: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    rename = "add"
+)]
+pub async fn stopwords_add(
+    context: Context<'_>,
+    #[description = "The words to add to the server's custom stop-words, separated by commas."]
+    words: String,
+) -> Result<(), Error> {
+    let guild_container_id = match get_guild_container_id(&context) {
+        Ok(guild_container_id) => guild_container_id,
+        Err(error) => return send_error_message(&context, &error).await,
+    };
+
+    let words = split_words(&words);
+    if let Err(e) = context
+        .data()
+        .processor
+        .add_custom_stopwords(&guild_container_id, &words)
+        .await
+    {
+        return send_error_message(&context, &e.to_string()).await;
+    }
+
+    context
+        .say(format!("Added {} custom stop-words!", words.len()))
+        .await?;
+
+    Ok(())
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    rename = "remove"
+)]
+pub async fn stopwords_remove(
+    context: Context<'_>,
+    #[description = "The words to remove from the server's custom stop-words, separated by commas."]
+    words: String,
+) -> Result<(), Error> {
+    let guild_container_id = match get_guild_container_id(&context) {
+        Ok(guild_container_id) => guild_container_id,
+        Err(error) => return send_error_message(&context, &error).await,
+    };
+
+    let words = split_words(&words);
+    if let Err(e) = context
+        .data()
+        .processor
+        .remove_custom_stopwords(&guild_container_id, &words)
+        .await
+    {
+        return send_error_message(&context, &e.to_string()).await;
+    }
+
+    context
+        .say(format!("Removed {} custom stop-words!", words.len()))
+        .await?;
+
+    Ok(())
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    rename = "list"
+)]
+pub async fn stopwords_list(context: Context<'_>) -> Result<(), Error> {
+    let guild_container_id = match get_guild_container_id(&context) {
+        Ok(guild_container_id) => guild_container_id,
+        Err(error) => return send_error_message(&context, &error).await,
+    };
+
+    let stopwords = match context
+        .data()
+        .processor
+        .get_custom_stopwords(&guild_container_id)
+        .await
+    {
+        Ok(stopwords) => stopwords,
+        Err(e) => return send_error_message(&context, &e.to_string()).await,
+    };
+
+    if stopwords.is_empty() {
+        context.say("This server has no custom stop-words!").await?;
+        return Ok(());
+    }
+
+    let stopwords: Vec<String> = stopwords
+        .iter()
+        .map(|stopword| format!("`{}`", stopword))
+        .collect();
+    context
+        .say(format!(
+            "This server's custom stop-words are {}",
+            get_list_text(&stopwords)
+        ))
+        .await?;
+
+    Ok(())
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    subcommands("digest_set", "digest_remove", "digest_show", "digest_preview"),
+    subcommand_required
+)]
+#[allow(clippy::unused_async)]
+pub async fn digest(_context: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+fn get_next_run_text(schedule: &DigestSchedule) -> String {
+    get_next_run_time(schedule).map_or_else(
+        || String::from("never"),
+        |next_run_time| format!("<t:{}:F>", next_run_time.timestamp()),
+    )
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    rename = "set"
+)]
+pub async fn digest_set(
+    context: Context<'_>,
+    #[description = "The channel to post the digest to."] channel: GuildChannel,
+    #[description = "A cron expression in UTC, e.g. `0 18 * * Fri` for every Friday at 18:00."]
+    schedule: String,
+) -> Result<(), Error> {
+    let guild_container_id = match get_guild_container_id(&context) {
+        Ok(guild_container_id) => guild_container_id,
+        Err(error) => return send_error_message(&context, &error).await,
+    };
+    if channel.guild_id.to_string() != guild_container_id {
+        return send_error_message(&context, "The channel must be in this server!").await;
+    }
+    if let Err(e) = parse_schedule(&schedule) {
+        return send_error_message(
+            &context,
+            &format!("The schedule is not a valid cron expression: {}", e),
+        )
+        .await;
+    }
+
+    let digest_schedule = DigestSchedule {
+        container_id: guild_container_id,
+        channel_id: channel.id.to_string(),
+        schedule,
+        last_run_time: chrono::Utc::now().timestamp(),
+    };
+    if let Err(e) = context
+        .data()
+        .processor
+        .set_digest_schedule(&digest_schedule)
+        .await
+    {
+        return send_error_message(&context, &e.to_string()).await;
+    }
+
+    context
+        .say(format!(
+            "The digest will be posted to {} next on {}!",
+            channel,
+            get_next_run_text(&digest_schedule)
+        ))
+        .await?;
+
+    Ok(())
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    rename = "remove"
+)]
+pub async fn digest_remove(context: Context<'_>) -> Result<(), Error> {
+    let guild_container_id = match get_guild_container_id(&context) {
+        Ok(guild_container_id) => guild_container_id,
+        Err(error) => return send_error_message(&context, &error).await,
+    };
+
+    if let Err(e) = context
+        .data()
+        .processor
+        .remove_digest_schedule(&guild_container_id)
+        .await
+    {
+        return send_error_message(&context, &e.to_string()).await;
+    }
+
+    context.say("The digest will no longer be posted!").await?;
+
+    Ok(())
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    rename = "show"
+)]
+pub async fn digest_show(context: Context<'_>) -> Result<(), Error> {
+    let guild_container_id = match get_guild_container_id(&context) {
+        Ok(guild_container_id) => guild_container_id,
+        Err(error) => return send_error_message(&context, &error).await,
+    };
+
+    let digest_schedule = match context
+        .data()
+        .processor
+        .get_digest_schedule(&guild_container_id)
+        .await
+    {
+        Ok(Some(digest_schedule)) => digest_schedule,
+        Ok(None) => {
+            context.say("This server has no digest schedule!").await?;
+            return Ok(());
+        }
+        Err(e) => return send_error_message(&context, &e.to_string()).await,
+    };
+
+    context
+        .say(format!(
+            "The digest is posted to <#{}> on the schedule `{}`, next on {}",
+            digest_schedule.channel_id,
+            digest_schedule.schedule,
+            get_next_run_text(&digest_schedule)
+        ))
+        .await?;
+
+    Ok(())
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    rename = "preview"
+)]
+pub async fn digest_preview(context: Context<'_>) -> Result<(), Error> {
+    // This command can take some time
+    if let Err(error) = context.defer().await {
+        return send_error_message(&context, &error.to_string()).await;
+    }
+
+    let guild_container_id = match get_guild_container_id(&context) {
+        Ok(guild_container_id) => guild_container_id,
+        Err(error) => return send_error_message(&context, &error).await,
+    };
+
+    let message = match create_digest_message(&context.data().processor, &guild_container_id).await
+    {
+        Ok(Some(message)) => message,
+        Ok(None) => {
+            context
+                .say("Nothing happened in this server this week!")
+                .await?;
+            return Ok(());
+        }
+        Err(e) => return send_error_message(&context, &e.to_string()).await,
+    };
+
+    let mut reply = CreateReply::default()
+        .content(message.content)
+        .allowed_mentions(CreateAllowedMentions::new());
+    if let Some(chart) = message.chart {
+        reply = reply.attachment(CreateAttachment::bytes(chart, "chart.png"));
+    }
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    subcommands(
+        "nlp_settings_show",
+        "nlp_settings_context",
+        "nlp_settings_amount",
+        "nlp_settings_stopwords",
+        "nlp_settings_locale",
+        "nlp_settings_exclude",
+        "nlp_settings_include",
+        "nlp_settings_digest_channel"
+    ),
+    subcommand_required
+)]
+#[allow(clippy::unused_async)]
+pub async fn nlp_settings(_context: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Applies the update to the settings of the guild the command was used in
+async fn update_guild_settings(
+    context: &Context<'_>,
+    update: impl FnOnce(&mut GuildSettings) + Send,
+) -> Result<(), String> {
+    let mut settings = get_guild_settings(context).await?;
+    if settings.container_id.is_empty() {
+        return Err("You can't use this command in a DM!".into());
+    }
+    update(&mut settings);
+
+    context
+        .data()
+        .processor
+        .set_guild_settings(&settings)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+fn get_setting_text<T: std::fmt::Display>(setting: Option<&T>) -> String {
+    setting.map_or_else(|| String::from("not set"), |value| format!("`{}`", value))
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    rename = "show"
+)]
+pub async fn nlp_settings_show(context: Context<'_>) -> Result<(), Error> {
+    let settings = match get_guild_settings(&context).await {
+        Ok(settings) => settings,
+        Err(error) => return send_error_message(&context, &error).await,
+    };
+    let processor = &context.data().processor;
+    let excluded_container_ids = match processor
+        .get_excluded_container_ids(&settings.container_id)
+        .await
+    {
+        Ok(excluded_container_ids) => excluded_container_ids,
+        Err(e) => return send_error_message(&context, &e.to_string()).await,
+    };
+    let custom_stopwords = match processor.get_custom_stopwords(&settings.container_id).await {
+        Ok(custom_stopwords) => custom_stopwords,
+        Err(e) => return send_error_message(&context, &e.to_string()).await,
+    };
+    let digest_schedule = match processor.get_digest_schedule(&settings.container_id).await {
+        Ok(digest_schedule) => digest_schedule,
+        Err(e) => return send_error_message(&context, &e.to_string()).await,
+    };
+
+    let excluded_channels: Vec<String> = excluded_container_ids
+        .iter()
+        .map(|container_id| format!("<#{}>", container_id))
+        .collect();
+    let lines = [
+        String::from("This server's settings are"),
+        format!(
+            "- Default context: {}",
+            get_setting_text(settings.default_context.as_ref())
+        ),
+        format!(
+            "- Default amount: {}",
+            get_setting_text(settings.default_limit.as_ref())
+        ),
+        format!(
+            "- Default stop-words: {} with {} custom stop-words",
+            get_setting_text(settings.stopwords.as_ref()),
+            custom_stopwords.len()
+        ),
+        format!("- Locale: {}", get_setting_text(settings.locale.as_ref())),
+        format!(
+            "- Excluded channels and categories: {}",
+            if excluded_channels.is_empty() {
+                String::from("none")
+            } else {
+                get_list_text(&excluded_channels)
+            }
+        ),
+        format!(
+            "- Digest: {}",
+            digest_schedule.map_or_else(
+                || String::from("not scheduled"),
+                |digest_schedule| format!(
+                    "posted to <#{}> on the schedule `{}`",
+                    digest_schedule.channel_id, digest_schedule.schedule
+                )
+            )
+        ),
+    ];
+    context.say(lines.join("\n")).await?;
+
+    Ok(())
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    rename = "context"
+)]
+pub async fn nlp_settings_context(
+    context: Context<'_>,
+    #[description = "The context used when none is specified. Either `channel`, `server`, `discord` or `all`. Leave empty to reset."]
+    #[rename = "context"]
+    container_context: Option<String>,
+) -> Result<(), Error> {
+    if let Some(container_context) = &container_context {
+        if let Err(error) = get_container_ids_from_context(&context, Some(container_context)) {
+            return send_error_message(&context, &error).await;
+        }
+    }
+
+    let message = format!(
+        "The default context is now {}!",
+        get_setting_text(container_context.as_ref())
+    );
+    if let Err(error) = update_guild_settings(&context, |settings| {
+        settings.default_context = container_context;
+    })
+    .await
+    {
+        return send_error_message(&context, &error).await;
+    }
+    context.say(message).await?;
+
+    Ok(())
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    rename = "amount"
+)]
+pub async fn nlp_settings_amount(
+    context: Context<'_>,
+    #[description = "The amount of results used when none is specified. Leave empty to reset."]
+    amount: Option<u32>,
+) -> Result<(), Error> {
+    if amount == Some(0) {
+        return send_error_message(&context, "The amount must be at least 1!").await;
+    }
+
+    let message = format!(
+        "The default amount is now {}!",
+        get_setting_text(amount.as_ref())
+    );
+    if let Err(error) = update_guild_settings(&context, |settings| {
+        settings.default_limit = amount;
+    })
+    .await
+    {
+        return send_error_message(&context, &error).await;
+    }
+    context.say(message).await?;
+
+    Ok(())
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    rename = "stopwords"
+)]
+pub async fn nlp_settings_stopwords(
+    context: Context<'_>,
+    #[description = "The stop-words excluded when none are specified. Either `en`, `cs`, `es`, `de`, `custom` or `none`. Leave empty to reset."]
+    #[rename = "stopwords"]
+    stopwords_option: Option<String>,
+) -> Result<(), Error> {
+    if let Some(stopwords_option) = &stopwords_option {
+        if let Err(error) = check_stopwords_option(stopwords_option) {
+            return send_error_message(&context, &error).await;
+        }
+    }
+
+    let message = format!(
+        "The default stop-words are now {}!",
+        get_setting_text(stopwords_option.as_ref())
+    );
+    if let Err(error) = update_guild_settings(&context, |settings| {
+        settings.stopwords = stopwords_option;
+    })
+    .await
+    {
+        return send_error_message(&context, &error).await;
+    }
+    context.say(message).await?;
+
+    Ok(())
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    rename = "locale"
+)]
+pub async fn nlp_settings_locale(
+    context: Context<'_>,
+    #[description = "The locale used to format dates. Either `en-US`, `en-GB`, `cs`, `es` or `de`. Leave empty to reset."]
+    locale: Option<String>,
+) -> Result<(), Error> {
+    if let Some(locale) = &locale {
+        if Locale::from_str(locale).is_err() {
+            return send_error_message(
+                &context,
+                "The locale you specified was neither `en-US`, `en-GB`, `cs`, `es` nor `de`",
+            )
+            .await;
+        }
+    }
+
+    let message = format!("The locale is now {}!", get_setting_text(locale.as_ref()));
+    if let Err(error) = update_guild_settings(&context, |settings| {
+        settings.locale = locale;
+    })
+    .await
+    {
+        return send_error_message(&context, &error).await;
+    }
+    context.say(message).await?;
+
+    Ok(())
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    rename = "exclude"
+)]
+pub async fn nlp_settings_exclude(
+    context: Context<'_>,
+    #[description = "The channel or category to stop reading messages from."] channel: GuildChannel,
+    #[description = "Also delete the messages already stored from the channel or category."]
+    purge: Option<bool>,
+) -> Result<(), Error> {
+    // Purging can take some time
+    if let Err(error) = context.defer().await {
+        return send_error_message(&context, &error.to_string()).await;
+    }
+
+    let guild_container_id = match get_guild_container_id(&context) {
+        Ok(guild_container_id) => guild_container_id,
+        Err(error) => return send_error_message(&context, &error).await,
+    };
+    if channel.guild_id.to_string() != guild_container_id {
+        return send_error_message(&context, "The channel must be in this server!").await;
+    }
+
+    let processor = &context.data().processor;
+    if let Err(e) = processor
+        .add_excluded_container_id(&guild_container_id, &channel.id.to_string())
+        .await
+    {
+        return send_error_message(&context, &e.to_string()).await;
+    }
+
+    let mut deleted_count = 0;
+    for channel in get_channels_in_channel_or_category(&context, &channel) {
+        context.data().backfill.stop_channel(channel.id).await;
+        if purge.unwrap_or(false) {
+            match processor.forget_container(&channel.id.to_string()).await {
+                Ok(count) => deleted_count += count,
+                Err(e) => return send_error_message(&context, &e.to_string()).await,
+            }
+        }
+    }
+
+    context
+        .say(format!(
+            "Messages from {} are no longer read{}!",
+            channel,
+            if purge.unwrap_or(false) {
+                format!(" and {} stored messages were deleted", deleted_count)
+            } else {
+                String::new()
+            }
+        ))
+        .await?;
+
+    Ok(())
+}
+
+/// A category contains the channels whose parent it is, and the channels
+/// contain their active threads
+fn get_channels_in_channel_or_category(
+    context: &Context<'_>,
+    channel: &GuildChannel,
+) -> Vec<GuildChannel> {
+    context.guild().map_or_else(
+        || vec![channel.clone()],
+        |guild| {
+            let mut channels: Vec<GuildChannel> = if channel.kind == ChannelType::Category {
+                guild
+                    .channels
+                    .values()
+                    .filter(|guild_channel| guild_channel.parent_id == Some(channel.id))
+                    .cloned()
+                    .collect()
+            } else {
+                vec![channel.clone()]
+            };
+            let threads: Vec<GuildChannel> = guild
+                .threads
+                .iter()
+                .filter(|thread| {
+                    channels
+                        .iter()
+                        .any(|channel| thread.parent_id == Some(channel.id))
+                })
+                .cloned()
+                .collect();
+            channels.extend(threads);
+            channels
+        },
+    )
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    rename = "include"
+)]
+pub async fn nlp_settings_include(
+    context: Context<'_>,
+    #[description = "The excluded channel or category to read messages from again."]
+    channel: GuildChannel,
+) -> Result<(), Error> {
+    // Fetching archived threads can take some time
+    if let Err(error) = context.defer().await {
+        return send_error_message(&context, &error.to_string()).await;
+    }
+
+    let guild_container_id = match get_guild_container_id(&context) {
+        Ok(guild_container_id) => guild_container_id,
+        Err(error) => return send_error_message(&context, &error).await,
+    };
+    if channel.guild_id.to_string() != guild_container_id {
+        return send_error_message(&context, "The channel must be in this server!").await;
+    }
+
+    let processor = &context.data().processor;
+    if let Err(e) = processor
+        .remove_excluded_container_id(&guild_container_id, &channel.id.to_string())
+        .await
+    {
+        return send_error_message(&context, &e.to_string()).await;
+    }
+    let excluded_container_ids = match processor
+        .get_excluded_container_ids(&guild_container_id)
+        .await
+    {
+        Ok(excluded_container_ids) => excluded_container_ids,
+        Err(e) => return send_error_message(&context, &e.to_string()).await,
+    };
+
+    // The messages sent in the meantime are read like the ones of a new channel
+    let (threads, channels): (Vec<GuildChannel>, Vec<GuildChannel>) =
+        get_channels_in_channel_or_category(&context, &channel)
+            .into_iter()
+            .partition(is_thread);
+    let serenity_context = context.serenity_context();
+    let channels_to_read = match add_channels_to_read(
+        processor,
+        &serenity_context.http,
+        &channels,
+        &threads,
+        &excluded_container_ids,
+    )
+    .await
+    {
+        Ok(channels_to_read) => channels_to_read,
+        Err(e) => return send_error_message(&context, &e.to_string()).await,
+    };
+    tokio::spawn(context.data().backfill.clone().run(
+        serenity_context.http.clone(),
+        serenity_context.cache.clone(),
+        channel.guild_id,
+        channels_to_read,
+    ));
+
+    context
+        .say(format!(
+            "Messages from {} are read again, including the ones sent in the meantime!",
+            channel
+        ))
+        .await?;
+
+    Ok(())
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    rename = "digest_channel"
+)]
+pub async fn nlp_settings_digest_channel(
+    context: Context<'_>,
+    #[description = "The channel to post the digest to."] channel: GuildChannel,
+) -> Result<(), Error> {
+    let guild_container_id = match get_guild_container_id(&context) {
+        Ok(guild_container_id) => guild_container_id,
+        Err(error) => return send_error_message(&context, &error).await,
+    };
+    if channel.guild_id.to_string() != guild_container_id {
+        return send_error_message(&context, "The channel must be in this server!").await;
+    }
+
+    let processor = &context.data().processor;
+    let mut digest_schedule = match processor.get_digest_schedule(&guild_container_id).await {
+        Ok(Some(digest_schedule)) => digest_schedule,
+        Ok(None) => {
+            return send_error_message(
+                &context,
+                "This server has no digest schedule! Use `/digest set` first.",
+            )
+            .await;
+        }
+        Err(e) => return send_error_message(&context, &e.to_string()).await,
+    };
+    digest_schedule.channel_id = channel.id.to_string();
+    if let Err(e) = processor.set_digest_schedule(&digest_schedule).await {
+        return send_error_message(&context, &e.to_string()).await;
+    }
+
+    context
+        .say(format!("The digest will be posted to {}!", channel))
+        .await?;
+
+    Ok(())
+}
+
+#[poise::command(prefix_command, slash_command, required_permissions = "SEND_MESSAGES")]
+pub async fn nlp_optout(
+    context: Context<'_>,
+    #[description = "Set to `false` to have your messages analysed again."] opt_out: Option<bool>,
+) -> Result<(), Error> {
+    let sender_id = context.author().to_string();
+    let processor = &context.data().processor;
+    let opt_out = opt_out.unwrap_or(true);
+
+    let result = if opt_out {
+        processor.opt_out_sender(&sender_id).await
+    } else {
+        processor.opt_in_sender(&sender_id).await
+    };
+    if let Err(e) = result {
+        return send_error_message(&context, &e.to_string()).await;
+    }
+
+    let message = if opt_out {
+        "Your new messages will no longer be analysed! Use `/nlp_forget_me` to also delete the ones already stored."
+    } else {
+        "Your new messages will be analysed again!"
+    };
+    context
+        .send(CreateReply::default().content(message).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
+
+#[poise::command(prefix_command, slash_command, required_permissions = "SEND_MESSAGES")]
+pub async fn nlp_forget_me(
+    context: Context<'_>,
+    #[description = "Confirm that all your stored messages should be deleted."] confirm: bool,
+) -> Result<(), Error> {
+    if !confirm {
+        context
+            .send(
+                CreateReply::default()
+                    .content("Nothing was deleted!")
+                    .ephemeral(true),
+            )
+            .await?;
+        return Ok(());
+    }
+
+    // This command can take some time
+    if let Err(error) = context.defer_ephemeral().await {
+        return send_error_message(&context, &error.to_string()).await;
+    }
+
+    let deleted_count = match context
+        .data()
+        .processor
+        .forget_sender(&context.author().to_string())
+        .await
+    {
+        Ok(deleted_count) => deleted_count,
+        Err(e) => return send_error_message(&context, &e.to_string()).await,
+    };
+
+    context
+        .send(
+            CreateReply::default()
+                .content(format!(
+                    "Deleted {} of your messages! Use `/nlp_optout` to stop new ones from being analysed.",
+                    deleted_count
+                ))
+                .ephemeral(true),
+        )
+        .await?;
+
+    Ok(())
+}
+
+fn get_backfill_eta_text(progress: &GuildProgress) -> String {
+    if progress.is_finished() {
+        return String::from("finished");
+    }
+
+    progress.get_eta().map_or_else(
+        || String::from("unknown"),
+        |eta| {
+            let eta_seconds = i64::try_from(eta.as_secs()).unwrap_or(i64::MAX);
+            format!(
+                "<t:{}:R>",
+                chrono::Utc::now().timestamp().saturating_add(eta_seconds)
+            )
+        },
+    )
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD"
+)]
+pub async fn nlp_status(context: Context<'_>) -> Result<(), Error> {
+    let guild_id = match context.guild_id() {
+        Some(guild_id) => guild_id,
+        None => return send_error_message(&context, "You can't use this command in a DM!").await,
+    };
+
+    let progress = if let Some(progress) = context.data().backfill.get_progress(guild_id).await {
+        progress
+    } else {
+        context
+            .say("This server's message history hasn't been read yet!")
+            .await?;
+        return Ok(());
+    };
+
+    let lines = [
+        String::from("Reading this server's message history"),
+        format!(
+            "- Channels: {} of {} done, {} skipped",
+            progress.channels_done, progress.channels_total, progress.channels_skipped
+        ),
+        format!("- Messages read: {}", progress.messages_read),
+        format!("- Done: {}", get_backfill_eta_text(&progress)),
+    ];
+    context.say(lines.join("\n")).await?;
+
+    Ok(())
+}
@@ -1,4 +1,6 @@
 use crate::file::read_file_as_string;
+use nlp_bot_api::processor::entry::{AuthorKind, AuthorPolicy};
+use serenity::all::{Mention, UserId};
 use std::{fmt, io};
 
 pub enum Error {
@@ -27,10 +29,96 @@ impl fmt::Display for Error {
     }
 }
 
+fn default_sql_database_path() -> String {
+    String::from("./database.db")
+}
+
+const fn default_true() -> bool {
+    true
+}
+
+fn default_http_api_address() -> String {
+    String::from("127.0.0.1:8080")
+}
+
+/// Which messages are read. The allowed bots are read even if bots are
+/// ignored.
+#[derive(serde::Deserialize)]
+pub struct AuthorPolicyConfiguration {
+    #[serde(default = "default_true")]
+    pub ignore_bots: bool,
+    #[serde(default = "default_true")]
+    pub ignore_webhooks: bool,
+    /// Messages like joins, pins and boosts
+    #[serde(default = "default_true")]
+    pub ignore_system_messages: bool,
+    #[serde(default)]
+    pub allowed_bot_ids: Vec<u64>,
+}
+
+impl Default for AuthorPolicyConfiguration {
+    fn default() -> Self {
+        Self {
+            ignore_bots: true,
+            ignore_webhooks: true,
+            ignore_system_messages: true,
+            allowed_bot_ids: Vec::new(),
+        }
+    }
+}
+
+impl AuthorPolicyConfiguration {
+    pub fn to_author_policy(&self) -> AuthorPolicy {
+        let ignored_author_kinds = [
+            (self.ignore_bots, AuthorKind::Bot),
+            (self.ignore_webhooks, AuthorKind::Webhook),
+            (self.ignore_system_messages, AuthorKind::System),
+        ]
+        .iter()
+        .filter(|(ignored, _)| *ignored)
+        .map(|(_, author_kind)| *author_kind)
+        .collect();
+
+        // Sender IDs are stored as mentions
+        let allowed_sender_ids = self
+            .allowed_bot_ids
+            .iter()
+            .map(|id| Mention::from(UserId::new(*id)).to_string())
+            .collect();
+
+        AuthorPolicy {
+            ignored_author_kinds,
+            allowed_sender_ids,
+        }
+    }
+}
+
+/// The HTTP API is only served if the bot was compiled with the `http_api`
+/// feature.
+#[derive(serde::Deserialize)]
+#[cfg_attr(not(feature = "http_api"), allow(dead_code))]
+pub struct HttpApiConfiguration {
+    #[serde(default = "default_http_api_address")]
+    pub address: String,
+    /// Requests have to send it as `Authorization: Bearer <token>`
+    pub token: String,
+}
+
 #[derive(serde::Deserialize)]
 pub struct Configuration {
-    pub discord_token: String,
+    /// Only needed to start the bot, not to import exports
+    pub discord_token: Option<String>,
+    #[serde(default = "default_sql_database_path")]
     pub sql_database_path: String,
+    /// If set, a `PostgreSQL` database is used instead of `SQLite`
+    pub database_url: Option<String>,
+    /// Either `unicode` (default) or `legacy`
+    pub tokenizer: Option<String>,
+    #[serde(default)]
+    pub author_policy: AuthorPolicyConfiguration,
+    /// How many channels are read at once when catching up on history
+    pub backfill_concurrency: Option<usize>,
+    pub http_api: Option<HttpApiConfiguration>,
 }
 
 pub fn read_configuration_from_file(path: &String) -> Result<Configuration, Error> {
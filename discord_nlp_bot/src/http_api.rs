@@ -0,0 +1,362 @@
+use crate::commands::{split_ngram_contents, MAX_NGRAMS_IN_CHART};
+use crate::config::HttpApiConfiguration;
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use nlp_bot_api::displayers::chart::display_ngram_count_over_time;
+use nlp_bot_api::displayers::locale::Locale;
+use nlp_bot_api::processor::ngram::{Granularity, NgramSeriesForByContentCommand};
+use nlp_bot_api::processor::stopwords::Language;
+use nlp_bot_api::processor::Processor;
+use nlp_bot_api::store::filters::Order;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+
+#[derive(Clone)]
+struct ApiState {
+    processor: Arc<Processor>,
+    token: Arc<str>,
+}
+
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn bad_request(message: impl Display) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            message: message.to_string(),
+        }
+    }
+
+    fn internal(message: impl Display) -> Self {
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: message.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = Json(ErrorResponse {
+            error: self.message,
+        });
+
+        (self.status, body).into_response()
+    }
+}
+
+/// Containers are separated by commas and include their children. Without
+/// any, all containers are searched.
+#[derive(Deserialize)]
+struct NgramsByCountQuery {
+    sender: Option<String>,
+    length: Option<u32>,
+    limit: Option<u32>,
+    /// Either `asc` or `desc`
+    order: Option<String>,
+    containers: Option<String>,
+    /// Either `en`, `cs`, `es` or `de`
+    stopwords: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct NgramByContentQuery {
+    content: String,
+    sender: Option<String>,
+    containers: Option<String>,
+    /// Either `hour`, `day`, `week` or `month`
+    granularity: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChartQuery {
+    /// Separated by commas
+    ngrams: String,
+    sender: Option<String>,
+    containers: Option<String>,
+    granularity: Option<String>,
+    #[serde(default)]
+    normalise: bool,
+    /// Either `en-US`, `en-GB`, `cs`, `es` or `de`
+    locale: Option<String>,
+}
+
+#[derive(Serialize)]
+struct NgramResponse {
+    content: String,
+    count: u32,
+}
+
+/// The occurrences in the time bucket starting at `time` (a Unix timestamp)
+#[derive(Serialize)]
+struct NgramPointResponse {
+    count: u32,
+    time: i64,
+}
+
+#[derive(Serialize)]
+struct EntriesCountResponse {
+    container_id: String,
+    entries_count: i32,
+}
+
+#[derive(Serialize)]
+struct ContainerResponse {
+    container_id: String,
+    container_parent_id: String,
+}
+
+fn split_container_ids(containers: Option<&String>) -> Vec<String> {
+    containers
+        .map(|containers| {
+            containers
+                .split(',')
+                .map(str::trim)
+                .filter(|container_id| !container_id.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn parse_parameter<T>(value: Option<&String>, name: &str) -> Result<Option<T>, ApiError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    value
+        .map(|value| T::from_str(value))
+        .transpose()
+        .map_err(|e| ApiError::bad_request(format!("{}: {}", name, e)))
+}
+
+/// Expects `Authorization: Bearer <token>`. The token is compared in
+/// constant time, so that it can't be guessed from the response times.
+async fn authenticate(State(state): State<ApiState>, request: Request, next: Next) -> Response {
+    let is_authenticated = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map_or(false, |token| {
+            token.as_bytes().ct_eq(state.token.as_bytes()).into()
+        });
+    if !is_authenticated {
+        return ApiError {
+            status: StatusCode::UNAUTHORIZED,
+            message: String::from("Missing or invalid token"),
+        }
+        .into_response();
+    }
+
+    next.run(request).await
+}
+
+async fn get_ngrams_by_count(
+    State(state): State<ApiState>,
+    Query(query): Query<NgramsByCountQuery>,
+) -> Result<Json<Vec<NgramResponse>>, ApiError> {
+    let order: Option<Order> = parse_parameter(query.order.as_ref(), "order")?;
+    let language: Option<Language> = parse_parameter(query.stopwords.as_ref(), "stopwords")?;
+
+    let stopwords = state
+        .processor
+        .get_stopwords(language, None)
+        .await
+        .map_err(ApiError::internal)?;
+    let ngrams = state
+        .processor
+        .get_ngrams_by_count(
+            query.sender,
+            query.length,
+            query.limit,
+            &split_container_ids(query.containers.as_ref()),
+            order,
+            &stopwords,
+        )
+        .await
+        .map_err(ApiError::internal)?;
+
+    Ok(Json(
+        ngrams
+            .into_iter()
+            .map(|ngram| NgramResponse {
+                content: ngram.content,
+                count: ngram.count,
+            })
+            .collect(),
+    ))
+}
+
+async fn get_ngram_by_content(
+    State(state): State<ApiState>,
+    Query(query): Query<NgramByContentQuery>,
+) -> Result<Json<Vec<NgramPointResponse>>, ApiError> {
+    let granularity: Option<Granularity> =
+        parse_parameter(query.granularity.as_ref(), "granularity")?;
+
+    let ngrams = state
+        .processor
+        .get_ngram_by_content(
+            &query.content,
+            query.sender,
+            &split_container_ids(query.containers.as_ref()),
+            granularity,
+        )
+        .await
+        .map_err(ApiError::internal)?;
+
+    Ok(Json(
+        ngrams
+            .into_iter()
+            .map(|ngram| NgramPointResponse {
+                count: ngram.count,
+                time: ngram.time,
+            })
+            .collect(),
+    ))
+}
+
+async fn get_ngram_by_content_chart(
+    State(state): State<ApiState>,
+    Query(query): Query<ChartQuery>,
+) -> Result<Response, ApiError> {
+    let granularity: Option<Granularity> =
+        parse_parameter(query.granularity.as_ref(), "granularity")?;
+    let locale: Option<Locale> = parse_parameter(query.locale.as_ref(), "locale")?;
+
+    let ngram_contents = split_ngram_contents(&query.ngrams);
+    if ngram_contents.is_empty() || ngram_contents.len() > MAX_NGRAMS_IN_CHART {
+        return Err(ApiError::bad_request(format!(
+            "You must specify between 1 and {} n-grams!",
+            MAX_NGRAMS_IN_CHART
+        )));
+    }
+
+    let ngram_series: Vec<NgramSeriesForByContentCommand> = state
+        .processor
+        .get_ngrams_by_content(
+            &ngram_contents,
+            query.sender,
+            &split_container_ids(query.containers.as_ref()),
+            granularity,
+            query.normalise,
+        )
+        .await
+        .map_err(ApiError::internal)?
+        .into_iter()
+        .filter(|series| !series.ngrams.is_empty())
+        .collect();
+    if ngram_series.is_empty() {
+        return Err(ApiError {
+            status: StatusCode::NOT_FOUND,
+            message: String::from("No n-grams found!"),
+        });
+    }
+
+    let image = display_ngram_count_over_time(
+        &ngram_series,
+        granularity.unwrap_or_default(),
+        query.normalise,
+        locale,
+    )
+    .map_err(ApiError::internal)?;
+
+    Ok(([(header::CONTENT_TYPE, "image/png")], image).into_response())
+}
+
+async fn get_entries_count(
+    State(state): State<ApiState>,
+    Path(container_id): Path<String>,
+) -> Result<Json<EntriesCountResponse>, ApiError> {
+    let entries_count = state
+        .processor
+        .get_entries_count_in_container(&container_id)
+        .await
+        .map_err(ApiError::internal)?;
+
+    Ok(Json(EntriesCountResponse {
+        container_id,
+        entries_count,
+    }))
+}
+
+async fn get_child_containers(
+    State(state): State<ApiState>,
+    Path(container_id): Path<String>,
+) -> Result<Json<Vec<ContainerResponse>>, ApiError> {
+    let containers = state
+        .processor
+        .get_child_containers(&container_id)
+        .await
+        .map_err(ApiError::internal)?;
+
+    Ok(Json(
+        containers
+            .into_iter()
+            .map(|container| ContainerResponse {
+                container_id: container.container_id,
+                container_parent_id: container.container_parent_id,
+            })
+            .collect(),
+    ))
+}
+
+fn make_router(state: ApiState) -> Router {
+    Router::new()
+        .route("/ngrams_by_count", get(get_ngrams_by_count))
+        .route("/ngram_by_content", get(get_ngram_by_content))
+        .route(
+            "/ngram_by_content/chart.png",
+            get(get_ngram_by_content_chart),
+        )
+        .route(
+            "/containers/:container_id/entries_count",
+            get(get_entries_count),
+        )
+        .route(
+            "/containers/:container_id/children",
+            get(get_child_containers),
+        )
+        .route_layer(middleware::from_fn_with_state(state.clone(), authenticate))
+        .with_state(state)
+}
+
+/// Serves the API until it fails. Errors are logged.
+pub async fn serve(processor: Arc<Processor>, configuration: HttpApiConfiguration) {
+    let router = make_router(ApiState {
+        processor,
+        token: configuration.token.into(),
+    });
+    let listener = match tokio::net::TcpListener::bind(&configuration.address).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            log::warn!(
+                "Failed to serve the HTTP API on {}: {}",
+                configuration.address,
+                e
+            );
+            return;
+        }
+    };
+
+    log::info!("Serving the HTTP API on {}", configuration.address);
+    if let Err(e) = axum::serve(listener, router).await {
+        log::warn!("The HTTP API stopped: {}", e);
+    }
+}
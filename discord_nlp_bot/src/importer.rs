@@ -0,0 +1,323 @@
+use crate::file::read_file_as_string;
+use chrono::DateTime;
+use nlp_bot_api::processor::container::Container;
+use nlp_bot_api::processor::entry::{AuthorKind, Entry};
+use nlp_bot_api::processor::Processor;
+use serde::Deserialize;
+use serenity::all::{Mentionable, UserId};
+
+/// How many entries are added to the processor at once
+const ENTRY_BATCH_SIZE: usize = 1000;
+/// The guild `DiscordChatExporter` uses for direct messages
+const DIRECT_MESSAGES_GUILD_ID: &str = "0";
+const THREAD_CHANNEL_TYPES: [&str; 3] =
+    ["GuildPublicThread", "GuildPrivateThread", "GuildNewsThread"];
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// A channel exported with `DiscordChatExporter` in the JSON format. Only the
+/// fields needed for entries are read.
+#[derive(Deserialize)]
+struct Export {
+    guild: ExportGuild,
+    channel: ExportChannel,
+    messages: Vec<ExportMessage>,
+}
+
+#[derive(Deserialize)]
+struct ExportGuild {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportChannel {
+    id: String,
+    #[serde(rename = "type")]
+    kind: String,
+    /// The category for channels and the parent channel for threads
+    category_id: Option<String>,
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct ExportMessage {
+    id: String,
+    #[serde(rename = "type")]
+    kind: String,
+    timestamp: String,
+    content: String,
+    author: ExportAuthor,
+    reference: Option<ExportReference>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportAuthor {
+    id: String,
+    is_bot: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportReference {
+    message_id: Option<String>,
+}
+
+/// The export doesn't tell webhooks apart from bots
+fn get_author_kind(message: &ExportMessage) -> AuthorKind {
+    if message.author.is_bot {
+        return AuthorKind::Bot;
+    }
+
+    if message.kind != "Default" && message.kind != "Reply" {
+        return AuthorKind::System;
+    }
+
+    AuthorKind::User
+}
+
+fn make_entry(message: &ExportMessage, container_id: &str) -> Result<Entry, Error> {
+    // Message references are also used for crossposts, pins etc.
+    let parent_entry_id = if message.kind == "Reply" {
+        message
+            .reference
+            .as_ref()
+            .and_then(|reference| reference.message_id.clone())
+    } else {
+        None
+    };
+
+    Ok(Entry {
+        entry_id: message.id.clone(),
+        container_id: container_id.to_string(),
+        sender_id: message.author.id.parse::<UserId>()?.mention().to_string(),
+        unix_timestamp: DateTime::parse_from_rfc3339(&message.timestamp)?.timestamp(),
+        content: message.content.clone(),
+        parent_entry_id,
+        author_kind: get_author_kind(message),
+    })
+}
+
+/// Channels are children of their category and threads of the channel they
+/// were created in, like when the bot reads them. The category of the parent
+/// channel of a thread is not exported, so a parent channel which is already
+/// in the hierarchy is left where it is.
+async fn add_containers(processor: &Processor, export: &Export) -> Result<(), Error> {
+    let guild_id = &export.guild.id;
+    processor
+        .add_container(&Container {
+            container_id: guild_id.clone(),
+            container_parent_id: String::from("discord"),
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let parent_id = export.channel.category_id.as_ref();
+    if let Some(parent_id) = parent_id {
+        let parent_container = Container {
+            container_id: parent_id.clone(),
+            container_parent_id: guild_id.clone(),
+        };
+        if THREAD_CHANNEL_TYPES.contains(&export.channel.kind.as_str()) {
+            processor.add_container(&parent_container).await
+        } else {
+            processor.set_container_parent(&parent_container).await
+        }
+        .map_err(|e| e.to_string())?;
+    }
+
+    processor
+        .set_container_parent(&Container {
+            container_id: export.channel.id.clone(),
+            container_parent_id: parent_id.unwrap_or(guild_id).clone(),
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Channels excluded in the guild settings are skipped, directly or through
+/// their category (or their parent channel for threads).
+async fn is_export_excluded(processor: &Processor, export: &Export) -> Result<bool, Error> {
+    let excluded_container_ids = processor
+        .get_excluded_container_ids(&export.guild.id)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok([
+        Some(&export.channel.id),
+        export.channel.category_id.as_ref(),
+    ]
+    .iter()
+    .flatten()
+    .any(|id| excluded_container_ids.contains(id)))
+}
+
+/// Imports a channel exported with `DiscordChatExporter` in the JSON format.
+/// Returns the number of read messages.
+pub async fn import_file(processor: &Processor, path: &String) -> Result<usize, Error> {
+    let export: Export = serde_json::from_str(&read_file_as_string(path)?)?;
+
+    if export.guild.id == DIRECT_MESSAGES_GUILD_ID {
+        log::info!("Skipping direct messages in {}", path);
+        return Ok(0);
+    }
+    if is_export_excluded(processor, &export).await? {
+        log::info!(
+            "Skipping excluded container {} ({}) in {}",
+            export.channel.name,
+            export.channel.id,
+            path
+        );
+        return Ok(0);
+    }
+
+    add_containers(processor, &export).await?;
+
+    for messages in export.messages.chunks(ENTRY_BATCH_SIZE) {
+        let entries = messages
+            .iter()
+            .map(|message| make_entry(message, &export.channel.id))
+            .collect::<Result<Vec<Entry>, Error>>()?;
+        processor
+            .add_entries(entries.as_slice())
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    log::info!(
+        "Imported container {} ({}) of guild {} ({})",
+        export.channel.name,
+        export.channel.id,
+        export.guild.name,
+        export.guild.id
+    );
+
+    Ok(export.messages.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::import_file;
+    use nlp_bot_api::processor::entry::{AuthorKind, AuthorPolicy};
+    use nlp_bot_api::processor::tokenizer::TokenizerKind;
+    use nlp_bot_api::processor::Processor;
+    use nlp_bot_api::store::{Memory, Store};
+
+    const GUILD_ID: &str = "100000000000000001";
+    const CATEGORY_ID: &str = "100000000000000005";
+    const CHANNEL_ID: &str = "100000000000000010";
+    const THREAD_ID: &str = "100000000000000020";
+
+    fn get_fixture_path(name: &str) -> String {
+        format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    /// Ignores bots, webhooks and system messages, like the bot by default
+    fn make_processor() -> Processor {
+        Processor::<dyn Store>::new(
+            Box::new(Memory::new()),
+            TokenizerKind::default().into_tokenizer(),
+        )
+        .with_author_policy(AuthorPolicy {
+            ignored_author_kinds: vec![AuthorKind::Bot, AuthorKind::Webhook, AuthorKind::System],
+            allowed_sender_ids: Vec::new(),
+        })
+    }
+
+    async fn import(processor: &Processor, name: &str) -> usize {
+        import_file(processor, &get_fixture_path(name))
+            .await
+            .expect("Failed to import fixture")
+    }
+
+    async fn get_child_container_ids(processor: &Processor, container_id: &str) -> Vec<String> {
+        processor
+            .get_child_containers(container_id)
+            .await
+            .expect("Failed to get child containers")
+            .into_iter()
+            .map(|container| container.container_id)
+            .collect()
+    }
+
+    async fn get_entries_count(processor: &Processor) -> i32 {
+        processor
+            .get_entries_count_in_container(GUILD_ID)
+            .await
+            .expect("Failed to count entries")
+    }
+
+    async fn get_unigram_counts(processor: &Processor) -> Vec<String> {
+        let mut unigram_counts: Vec<String> = processor
+            .get_ngrams_by_count(None, Some(1), Some(100), &[GUILD_ID.to_string()], None, &[])
+            .await
+            .expect("Failed to get n-grams")
+            .into_iter()
+            .map(|ngram| format!("{}={}", ngram.content, ngram.count))
+            .collect();
+        unigram_counts.sort();
+
+        unigram_counts
+    }
+
+    #[tokio::test]
+    async fn import_channel() {
+        let processor = make_processor();
+
+        assert_eq!(import(&processor, "general.json").await, 4);
+
+        // The bot message and the pin are not stored
+        assert_eq!(get_entries_count(&processor).await, 2);
+        assert_eq!(
+            get_child_container_ids(&processor, "discord").await,
+            [GUILD_ID]
+        );
+        assert_eq!(
+            get_child_container_ids(&processor, GUILD_ID).await,
+            [CATEGORY_ID]
+        );
+        assert_eq!(
+            get_child_container_ids(&processor, CATEGORY_ID).await,
+            [CHANNEL_ID]
+        );
+    }
+
+    #[tokio::test]
+    async fn import_thread() {
+        let processor = make_processor();
+
+        import(&processor, "general.json").await;
+        assert_eq!(import(&processor, "general_thread.json").await, 1);
+
+        assert_eq!(get_entries_count(&processor).await, 3);
+        // The parent channel stays in its category
+        assert_eq!(
+            get_child_container_ids(&processor, CATEGORY_ID).await,
+            [CHANNEL_ID]
+        );
+        assert_eq!(
+            get_child_container_ids(&processor, CHANNEL_ID).await,
+            [THREAD_ID]
+        );
+    }
+
+    #[tokio::test]
+    async fn import_again() {
+        let processor = make_processor();
+
+        import(&processor, "general.json").await;
+        let unigram_counts = get_unigram_counts(&processor).await;
+        import(&processor, "general.json").await;
+
+        assert_eq!(get_entries_count(&processor).await, 2);
+        assert_eq!(get_unigram_counts(&processor).await, unigram_counts);
+        assert_eq!(
+            get_child_container_ids(&processor, GUILD_ID).await,
+            [CATEGORY_ID]
+        );
+    }
+}
@@ -1,15 +1,23 @@
+mod backfill;
 mod bot;
 mod commands;
 mod config;
 mod file;
+#[cfg(feature = "http_api")]
+mod http_api;
+mod importer;
 mod makers;
 mod message_formatters;
+mod scheduler;
 
+use backfill::Backfill;
 use bot::{start, Bot};
 use clap::Parser;
-use config::read_configuration_from_file;
+use config::{read_configuration_from_file, Configuration, HttpApiConfiguration};
+use nlp_bot_api::processor::tokenizer::TokenizerKind;
 use nlp_bot_api::processor::Processor;
-use nlp_bot_api::store::Sql;
+use nlp_bot_api::store::{Sql, Store};
+use std::str::FromStr;
 use std::sync::Arc;
 
 #[derive(clap::Parser, Debug)]
@@ -17,6 +25,64 @@ struct CommandLineArguments {
     /// The path to the configuration file
     #[arg(short, long, default_value = "./config.json")]
     configuration_file: String,
+    /// Drop the n-grams and recompute them from the stored entries
+    #[arg(long)]
+    rebuild_ngrams: bool,
+    /// Read the message history of every channel from the start, e.g. to
+    /// reclassify the authors of messages stored before their kind was known
+    #[arg(long)]
+    reread_history: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Import channels exported with `DiscordChatExporter` in the JSON format
+    /// instead of starting the bot
+    Import {
+        /// The exported JSON files
+        #[arg(required = true)]
+        paths: Vec<String>,
+    },
+}
+
+#[cfg(feature = "postgres")]
+async fn make_postgres_store(database_url: &str) -> Result<Box<dyn Store>, String> {
+    match nlp_bot_api::store::Postgres::new(database_url).await {
+        Ok(store) => Ok(Box::new(store)),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[cfg(not(feature = "postgres"))]
+#[allow(clippy::unused_async)]
+async fn make_postgres_store(_database_url: &str) -> Result<Box<dyn Store>, String> {
+    Err(String::from(
+        "The bot was compiled without the `postgres` feature",
+    ))
+}
+
+#[cfg(feature = "http_api")]
+fn start_http_api(processor: Arc<Processor>, configuration: HttpApiConfiguration) {
+    tokio::spawn(http_api::serve(processor, configuration));
+}
+
+#[cfg(not(feature = "http_api"))]
+#[allow(clippy::needless_pass_by_value)]
+fn start_http_api(_processor: Arc<Processor>, _configuration: HttpApiConfiguration) {
+    println!("The bot was compiled without the `http_api` feature, so the HTTP API is not served");
+}
+
+async fn make_store(configuration: &Configuration) -> Result<Box<dyn Store>, String> {
+    if let Some(database_url) = &configuration.database_url {
+        return make_postgres_store(database_url).await;
+    }
+
+    match Sql::new(&configuration.sql_database_path).await {
+        Ok(store) => Ok(Box::new(store)),
+        Err(e) => Err(e.to_string()),
+    }
 }
 
 #[tokio::main]
@@ -33,7 +99,29 @@ async fn main() {
             }
         };
 
-    let store = match Sql::new(&configuration.sql_database_path).await {
+    let tokenizer_kind = match configuration
+        .tokenizer
+        .as_deref()
+        .map(TokenizerKind::from_str)
+    {
+        None => TokenizerKind::default(),
+        Some(Ok(tokenizer_kind)) => tokenizer_kind,
+        Some(Err(e)) => {
+            println!("Failed to read tokenizer from configuration file: {}", e);
+            return;
+        }
+    };
+    // An empty token would let any request through
+    if configuration
+        .http_api
+        .as_ref()
+        .map_or(false, |http_api| http_api.token.is_empty())
+    {
+        println!("The HTTP API needs a token in the configuration file");
+        return;
+    }
+
+    let store = match make_store(&configuration).await {
         Ok(store) => store,
         Err(e) => {
             println!("Failed to construct store: {}", e);
@@ -41,14 +129,61 @@ async fn main() {
         }
     };
 
-    let processor = Arc::new(Processor::new(store));
-    let bot = Bot::new(processor.clone());
+    let processor = Arc::new(
+        Processor::new(store, tokenizer_kind.into_tokenizer())
+            .with_author_policy(configuration.author_policy.to_author_policy()),
+    );
+    if let Err(e) = processor.reset_ngrams_if_tokenizer_changed().await {
+        println!("Failed to check tokenizer: {}", e);
+        return;
+    }
+
+    if let Some(Command::Import { paths }) = &command_line_arguments.command {
+        for path in paths {
+            match importer::import_file(&processor, path).await {
+                Ok(messages_count) => println!("Read {} messages from {}", messages_count, path),
+                Err(e) => println!("Failed to import {}: {}", path, e),
+            }
+        }
+
+        // The n-grams of the entries stored before are dropped if the
+        // tokenizer changed
+        if let Err(e) = processor.cache_ngrams().await {
+            println!("Failed to cache n-grams: {}", e);
+        }
+        return;
+    }
+    let discord_token = if let Some(discord_token) = configuration.discord_token {
+        discord_token
+    } else {
+        println!("The configuration file has no `discord_token` to start the bot with");
+        return;
+    };
+
+    let backfill = Arc::new(
+        Backfill::new(
+            processor.clone(),
+            configuration
+                .backfill_concurrency
+                .unwrap_or(backfill::DEFAULT_CONCURRENCY),
+        )
+        .with_reread_history(command_line_arguments.reread_history),
+    );
+    let bot = Bot::new(processor.clone(), backfill.clone());
     let processor_for_caching_ngrams = processor.clone();
     let processor_for_bot = processor.clone();
 
+    if let Some(http_api_configuration) = configuration.http_api {
+        start_http_api(processor.clone(), http_api_configuration);
+    }
+
     log::info!("Starting bot...");
-    tokio::spawn(async move { processor_for_caching_ngrams.cache_ngrams().await });
-    if let Err(e) = start(bot, processor_for_bot, configuration.discord_token).await {
+    if command_line_arguments.rebuild_ngrams {
+        tokio::spawn(async move { processor_for_caching_ngrams.rebuild_ngrams().await });
+    } else {
+        tokio::spawn(async move { processor_for_caching_ngrams.cache_ngrams().await });
+    }
+    if let Err(e) = start(bot, processor_for_bot, backfill, discord_token).await {
         println!("Client error: {}", e);
-    };
+    }
 }
@@ -1,5 +1,44 @@
-use nlp_bot_api::processor::entry::Entry;
-use serenity::all::Message;
+use nlp_bot_api::processor::entry::{AuthorKind, Entry};
+use serenity::all::{Message, MessageType};
+
+const fn get_author_kind(discord_message: &Message) -> AuthorKind {
+    // Webhook messages have a bot author too
+    if discord_message.webhook_id.is_some() {
+        return AuthorKind::Webhook;
+    }
+
+    if discord_message.author.bot {
+        return AuthorKind::Bot;
+    }
+
+    if discord_message.author.system
+        || !matches!(
+            discord_message.kind,
+            MessageType::Regular | MessageType::InlineReply
+        )
+    {
+        return AuthorKind::System;
+    }
+
+    AuthorKind::User
+}
+
+fn get_parent_entry_id(discord_message: &Message) -> Option<String> {
+    if let Some(referenced_message) = &discord_message.referenced_message {
+        return Some(referenced_message.id.to_string());
+    }
+
+    // Message references are also used for crossposts, pins etc.
+    if discord_message.kind != MessageType::InlineReply {
+        return None;
+    }
+
+    discord_message
+        .message_reference
+        .as_ref()
+        .and_then(|reference| reference.message_id)
+        .map(|message_id| message_id.to_string())
+}
 
 pub fn make_entry(discord_message: &Message) -> Entry {
     Entry {
@@ -8,5 +47,7 @@ pub fn make_entry(discord_message: &Message) -> Entry {
         sender_id: discord_message.author.to_string(),
         unix_timestamp: discord_message.timestamp.unix_timestamp(),
         entry_id: discord_message.id.to_string(),
+        parent_entry_id: get_parent_entry_id(discord_message),
+        author_kind: get_author_kind(discord_message),
     }
 }
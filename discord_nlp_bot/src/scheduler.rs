@@ -0,0 +1,210 @@
+use crate::commands::get_locale;
+use crate::message_formatters::format_table;
+use chrono::{DateTime, TimeZone, Utc};
+use cron::Schedule;
+use nlp_bot_api::displayers::{
+    ascii_table::display_trending_ngram_list, chart::display_ngram_count_over_time,
+};
+use nlp_bot_api::processor::digest::{Digest, DigestSchedule};
+use nlp_bot_api::processor::ngram::{Granularity, NgramSeriesForByContentCommand};
+use nlp_bot_api::processor::Processor;
+use serenity::all::{ChannelId, CreateAllowedMentions, CreateAttachment, CreateMessage, Http};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often the schedules are checked for due digests
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+pub struct DigestMessage {
+    pub content: String,
+    pub chart: Option<Vec<u8>>,
+}
+
+/// Parses a cron expression evaluated in UTC. Besides the usual five fields
+/// (minute, hour, day of month, month and day of week), the six and seven
+/// field forms with seconds and years are accepted.
+pub fn parse_schedule(schedule: &str) -> Result<Schedule, String> {
+    let schedule = if schedule.split_whitespace().count() == 5 {
+        format!("0 {}", schedule)
+    } else {
+        schedule.to_string()
+    };
+
+    Schedule::from_str(&schedule).map_err(|e| e.to_string())
+}
+
+/// Returns the first run of the schedule after its last run
+pub fn get_next_run_time(schedule: &DigestSchedule) -> Option<DateTime<Utc>> {
+    let last_run_time = Utc.timestamp_opt(schedule.last_run_time, 0).single()?;
+
+    parse_schedule(&schedule.schedule)
+        .ok()?
+        .after(&last_run_time)
+        .next()
+}
+
+fn get_active_senders_text(digest: &Digest) -> String {
+    digest
+        .active_senders
+        .iter()
+        .enumerate()
+        .map(|(index, sender)| {
+            format!(
+                "{}. {} with {} message{}",
+                index + 1,
+                sender.sender_id,
+                sender.count,
+                if sender.count == 1 { "" } else { "s" }
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Returns `None` if nothing happened in the container in the last week.
+pub async fn create_digest_message(
+    processor: &Processor,
+    container_id: &str,
+) -> Result<Option<DigestMessage>, Error> {
+    let digest = processor
+        .get_digest(container_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    if digest.new_ngrams.is_empty()
+        && digest.trending_ngrams.is_empty()
+        && digest.active_senders.is_empty()
+    {
+        return Ok(None);
+    }
+
+    let mut sections = vec![String::from("**Here's what happened this week**")];
+    if !digest.new_ngrams.is_empty() {
+        sections.push(format_table(
+            &display_trending_ngram_list(&digest.new_ngrams),
+            "New n-grams",
+        ));
+    }
+    if !digest.trending_ngrams.is_empty() {
+        sections.push(format_table(
+            &display_trending_ngram_list(&digest.trending_ngrams),
+            "Trending n-grams",
+        ));
+    }
+    if !digest.active_senders.is_empty() {
+        sections.push(format!(
+            "Most active senders\n{}",
+            get_active_senders_text(&digest)
+        ));
+    }
+
+    let ngram_series: Vec<NgramSeriesForByContentCommand> = digest
+        .ngram_series
+        .into_iter()
+        .filter(|series| !series.ngrams.is_empty())
+        .collect();
+    let chart = if ngram_series.is_empty() {
+        None
+    } else {
+        let settings = processor
+            .get_guild_settings(container_id)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Some(
+            display_ngram_count_over_time(
+                &ngram_series,
+                Granularity::Week,
+                false,
+                get_locale(&settings),
+            )
+            .map_err(|e| e.to_string())?,
+        )
+    };
+
+    Ok(Some(DigestMessage {
+        content: sections.join("\n"),
+        chart,
+    }))
+}
+
+async fn post_digest(
+    http: &Http,
+    processor: &Processor,
+    schedule: &DigestSchedule,
+) -> Result<(), Error> {
+    let channel_id = ChannelId::new(schedule.channel_id.parse()?);
+    let message =
+        if let Some(message) = create_digest_message(processor, &schedule.container_id).await? {
+            message
+        } else {
+            log::info!(
+                "Skipping the empty digest of container {}",
+                schedule.container_id
+            );
+            return Ok(());
+        };
+
+    // The senders are mentioned, but shouldn't be pinged every week
+    let mut create_message = CreateMessage::new()
+        .content(message.content)
+        .allowed_mentions(CreateAllowedMentions::new());
+    if let Some(chart) = message.chart {
+        create_message = create_message.add_file(CreateAttachment::bytes(chart, "chart.png"));
+    }
+    channel_id.send_message(http, create_message).await?;
+
+    Ok(())
+}
+
+async fn post_due_digests(http: &Http, processor: &Processor) {
+    let schedules = match processor.get_digest_schedules().await {
+        Ok(schedules) => schedules,
+        Err(e) => {
+            log::error!("Failed to get digest schedules: {}", e);
+            return;
+        }
+    };
+
+    let now = Utc::now();
+    for schedule in schedules {
+        if get_next_run_time(&schedule).map_or(true, |next_run_time| next_run_time > now) {
+            continue;
+        }
+
+        // The run is recorded first, so that a digest which fails to post is
+        // not retried every minute
+        if let Err(e) = processor
+            .set_digest_last_run_time(&schedule.container_id, now.timestamp())
+            .await
+        {
+            log::error!(
+                "Failed to record the digest run of container {}: {}",
+                schedule.container_id,
+                e
+            );
+            continue;
+        }
+
+        log::info!("Posting the digest of container {}", schedule.container_id);
+        if let Err(e) = post_digest(http, processor, &schedule).await {
+            log::warn!(
+                "Failed to post the digest of container {}: {}",
+                schedule.container_id,
+                e
+            );
+        }
+    }
+}
+
+/// Posts the digests which are due. A digest missed while the bot was offline
+/// is posted once when it comes back.
+pub async fn run(http: Arc<Http>, processor: Arc<Processor>) {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+        post_due_digests(&http, &processor).await;
+    }
+}
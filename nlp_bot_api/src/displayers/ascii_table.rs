@@ -1,4 +1,10 @@
-use crate::processor::ngram::NgramForByCountCommand;
+use crate::processor::{
+    entry::ReplyPairForRepliesCommand,
+    ngram::{
+        NgramForByCountCommand, NgramForCollocationsCommand, NgramForDistinctiveCommand,
+        NgramForTrendingCommand,
+    },
+};
 use ascii_table::AsciiTable;
 
 pub fn display_ngram_list(ngrams: &[NgramForByCountCommand]) -> String {
@@ -14,3 +20,91 @@ pub fn display_ngram_list(ngrams: &[NgramForByCountCommand]) -> String {
 
     table.format(data)
 }
+
+pub fn display_collocation_list(ngrams: &[NgramForCollocationsCommand]) -> String {
+    let mut table = AsciiTable::default();
+
+    table.column(0).set_header("N-gram");
+    table.column(1).set_header("Count");
+    table.column(2).set_header("PMI");
+
+    let data: Vec<Vec<String>> = ngrams
+        .iter()
+        .map(|ngram: &NgramForCollocationsCommand| {
+            vec![
+                ngram.content.clone(),
+                ngram.count.to_string(),
+                format!("{:.2}", ngram.score),
+            ]
+        })
+        .collect();
+
+    table.format(data)
+}
+
+pub fn display_trending_ngram_list(ngrams: &[NgramForTrendingCommand]) -> String {
+    let mut table = AsciiTable::default();
+
+    table.column(0).set_header("N-gram");
+    table.column(1).set_header("Recent");
+    table.column(2).set_header("Baseline");
+    table.column(3).set_header("Growth");
+
+    let data: Vec<Vec<String>> = ngrams
+        .iter()
+        .map(|ngram: &NgramForTrendingCommand| {
+            vec![
+                ngram.content.clone(),
+                ngram.count.to_string(),
+                ngram.baseline_count.to_string(),
+                format!("{:.1}x", ngram.growth),
+            ]
+        })
+        .collect();
+
+    table.format(data)
+}
+
+pub fn display_distinctive_ngram_list(ngrams: &[NgramForDistinctiveCommand]) -> String {
+    let mut table = AsciiTable::default();
+
+    table.column(0).set_header("N-gram");
+    table.column(1).set_header("Count");
+    table.column(2).set_header("Elsewhere");
+    table.column(3).set_header("Score");
+
+    let data: Vec<Vec<String>> = ngrams
+        .iter()
+        .map(|ngram: &NgramForDistinctiveCommand| {
+            vec![
+                ngram.content.clone(),
+                ngram.count.to_string(),
+                ngram.background_count.to_string(),
+                format!("{:.1}", ngram.score),
+            ]
+        })
+        .collect();
+
+    table.format(data)
+}
+
+pub fn display_reply_pair_list(reply_pairs: &[ReplyPairForRepliesCommand]) -> String {
+    let mut table = AsciiTable::default();
+
+    table.column(0).set_header("Sender");
+    table.column(1).set_header("Replying to");
+    table.column(2).set_header("Count");
+
+    let data: Vec<Vec<String>> = reply_pairs
+        .iter()
+        .map(|reply_pair: &ReplyPairForRepliesCommand| {
+            vec![
+                reply_pair.sender_id.clone(),
+                reply_pair.parent_sender_id.clone(),
+                reply_pair.count.to_string(),
+            ]
+        })
+        .collect();
+
+    table.format(data)
+}
@@ -1,17 +1,30 @@
-use super::utils::ngram_time_to_date;
-use crate::processor::ngram::NgramsForByContentCommand;
+use super::locale::Locale;
+use super::utils::ngram_time_to_date_time;
+use crate::processor::ngram::{Granularity, NgramSeriesForByContentCommand};
 use image::{ImageError, ImageFormat, RgbImage};
 use plotters::{
     backend::BitMapBackend,
-    chart::ChartBuilder,
+    chart::{ChartBuilder, SeriesLabelPosition},
+    coord::types::RangedDateTime,
     drawing::{DrawingAreaErrorKind, IntoDrawingArea},
+    element::PathElement,
     series::LineSeries,
-    style::full_palette::{RED, WHITE},
+    style::{
+        full_palette::{
+            AMBER, BLACK, BLUE, BROWN, CYAN, GREEN, GREY, INDIGO, ORANGE, PINK, PURPLE, RED, WHITE,
+        },
+        Color, RGBColor,
+    },
 };
 use std::io::Cursor;
 
 const CHART_WIDTH: u32 = 1920;
 const CHART_HEIGHT: u32 = 1080;
+const LEGEND_LINE_LENGTH: i32 = 40;
+/// The colours of the lines; if there are more lines, the colours repeat
+const SERIES_COLORS: [RGBColor; 12] = [
+    RED, BLUE, GREEN, ORANGE, PURPLE, CYAN, BROWN, PINK, INDIGO, AMBER, GREY, BLACK,
+];
 
 #[derive(Debug)]
 pub enum Error {
@@ -56,11 +69,39 @@ impl From<ImageError> for Error {
     }
 }
 
+/// Without a locale, the dates are formatted as in ISO 8601
+const fn get_time_label_format(granularity: Granularity, locale: Option<Locale>) -> &'static str {
+    if let Some(locale) = locale {
+        return locale.get_date_format(granularity);
+    }
+
+    match granularity {
+        Granularity::Hour => "%Y-%m-%d %H:00",
+        Granularity::Day | Granularity::Week => "%Y-%m-%d",
+        Granularity::Month => "%Y-%m",
+    }
+}
+
 #[allow(clippy::missing_panics_doc)]
+/// If `normalised` is set, the values are expected to be occurrences per 1,000
+/// tokens.
 pub fn display_ngram_count_over_time(
-    ngrams: &[NgramsForByContentCommand],
+    ngram_series: &[NgramSeriesForByContentCommand],
+    granularity: Granularity,
+    normalised: bool,
+    locale: Option<Locale>,
 ) -> Result<Vec<u8>, Error> {
-    let (first_ngram, last_ngram) = match (ngrams.first(), ngrams.last()) {
+    let first_time = ngram_series
+        .iter()
+        .filter_map(|series| series.ngrams.first())
+        .map(|ngram| ngram.time)
+        .min();
+    let last_time = ngram_series
+        .iter()
+        .filter_map(|series| series.ngrams.last())
+        .map(|ngram| ngram.time)
+        .max();
+    let (first_time, last_time) = match (first_time, last_time) {
         (Some(f), Some(l)) => (f, l),
         _ => {
             return Err(Error::InvalidParameter(String::from(
@@ -68,6 +109,11 @@ pub fn display_ngram_count_over_time(
             )))
         }
     };
+    let max_value = ngram_series
+        .iter()
+        .flat_map(|series| series.ngrams.iter())
+        .map(|ngram| ngram.value)
+        .fold(0.0, f64::max);
 
     let mut image_buffer = vec![0; CHART_WIDTH as usize * CHART_HEIGHT as usize * 3];
     {
@@ -82,8 +128,10 @@ pub fn display_ngram_count_over_time(
             .x_label_area_size(100)
             .y_label_area_size(100)
             .build_cartesian_2d(
-                ngram_time_to_date(first_ngram.time)..ngram_time_to_date(last_ngram.time),
-                0..ngrams.iter().map(|ngram| ngram.count).max().unwrap_or(0),
+                RangedDateTime::from(
+                    ngram_time_to_date_time(first_time)..ngram_time_to_date_time(last_time),
+                ),
+                0.0..max_value,
             )?;
         chart
             .configure_mesh()
@@ -91,16 +139,55 @@ pub fn display_ngram_count_over_time(
             .x_desc("Time")
             .x_label_style(("Ubuntu Medium", 20))
             .x_labels(15)
-            .y_desc("Number of occurrences")
+            .x_label_formatter(&|time| {
+                time.format(get_time_label_format(granularity, locale))
+                    .to_string()
+            })
+            .y_desc(if normalised {
+                "Occurrences per 1,000 tokens"
+            } else {
+                "Number of occurrences"
+            })
             .y_label_style(("Ubuntu Medium", 20))
+            .y_label_formatter(&|value| {
+                if normalised {
+                    format!("{:.1}", value)
+                } else {
+                    format!("{:.0}", value)
+                }
+            })
             .y_labels(20)
             .draw()?;
-        chart.draw_series(LineSeries::new(
-            ngrams
-                .iter()
-                .map(|ngram| (ngram_time_to_date(ngram.time), ngram.count)),
-            &RED,
-        ))?;
+        for (index, series) in ngram_series.iter().enumerate() {
+            let color = SERIES_COLORS[index % SERIES_COLORS.len()];
+            chart
+                .draw_series(
+                    LineSeries::new(
+                        series
+                            .ngrams
+                            .iter()
+                            .map(|ngram| (ngram_time_to_date_time(ngram.time), ngram.value)),
+                        color.stroke_width(2),
+                    )
+                    // Mark the points, so that series with a single point are visible
+                    .point_size(3),
+                )?
+                .label(series.content.as_str())
+                .legend(move |(x, y)| {
+                    PathElement::new(
+                        vec![(x, y), (x + LEGEND_LINE_LENGTH, y)],
+                        color.stroke_width(2),
+                    )
+                });
+        }
+        chart
+            .configure_series_labels()
+            .position(SeriesLabelPosition::UpperRight)
+            .legend_area_size(LEGEND_LINE_LENGTH + 10)
+            .label_font(("Ubuntu Medium", 30))
+            .background_style(WHITE)
+            .border_style(BLACK)
+            .draw()?;
 
         drawing_area.present()?;
     }
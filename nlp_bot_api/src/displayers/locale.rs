@@ -0,0 +1,46 @@
+use crate::processor::ngram::Granularity;
+use std::str::FromStr;
+
+/// Determines how dates are formatted
+#[derive(Debug, Clone, Copy)]
+pub enum Locale {
+    AmericanEnglish,
+    BritishEnglish,
+    Czech,
+    Spanish,
+    German,
+}
+
+impl FromStr for Locale {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "en-US" => Ok(Self::AmericanEnglish),
+            "en-GB" => Ok(Self::BritishEnglish),
+            "cs" => Ok(Self::Czech),
+            "es" => Ok(Self::Spanish),
+            "de" => Ok(Self::German),
+            _ => Err(String::from("Invalid locale")),
+        }
+    }
+}
+
+impl Locale {
+    pub const fn get_date_format(self, granularity: Granularity) -> &'static str {
+        match (self, granularity) {
+            (Self::AmericanEnglish, Granularity::Hour) => "%m/%d/%Y %H:00",
+            (Self::AmericanEnglish, Granularity::Day | Granularity::Week) => "%m/%d/%Y",
+            (Self::BritishEnglish | Self::Spanish, Granularity::Hour) => "%d/%m/%Y %H:00",
+            (Self::BritishEnglish | Self::Spanish, Granularity::Day | Granularity::Week) => {
+                "%d/%m/%Y"
+            }
+            (Self::Czech, Granularity::Hour) => "%d. %m. %Y %H:00",
+            (Self::Czech, Granularity::Day | Granularity::Week) => "%d. %m. %Y",
+            (Self::German, Granularity::Hour) => "%d.%m.%Y %H:00",
+            (Self::German, Granularity::Day | Granularity::Week) => "%d.%m.%Y",
+            (Self::German, Granularity::Month) => "%m.%Y",
+            (_, Granularity::Month) => "%m/%Y",
+        }
+    }
+}
@@ -1,3 +1,4 @@
 pub mod ascii_table;
 pub mod chart;
+pub mod locale;
 mod utils;
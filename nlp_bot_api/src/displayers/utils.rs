@@ -1,7 +1,7 @@
-use chrono::{DateTime, NaiveDate};
+use chrono::{DateTime, NaiveDateTime};
 
-pub fn ngram_time_to_date(time: i64) -> NaiveDate {
+pub fn ngram_time_to_date_time(time: i64) -> NaiveDateTime {
     DateTime::from_timestamp(time, 0)
         .expect("You are reaching a little to far into the future!")
-        .date_naive()
+        .naive_utc()
 }
@@ -0,0 +1,4 @@
+ALTER TABLE
+    entries
+ADD
+    parent_entry_id TEXT;
@@ -0,0 +1,4 @@
+CREATE TABLE metadata (
+    key TEXT NOT NULL UNIQUE PRIMARY KEY,
+    value TEXT NOT NULL
+);
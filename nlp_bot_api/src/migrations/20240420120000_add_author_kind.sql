@@ -0,0 +1,40 @@
+-- Entries stored before author kinds were tracked are treated as sent by users
+ALTER TABLE
+    entries
+ADD
+    author_kind TEXT NOT NULL DEFAULT 'user';
+
+CREATE TABLE new_ngrams (
+    count INTEGER NOT NULL,
+    content TEXT NOT NULL,
+    length INTEGER NOT NULL,
+    time INTEGER NOT NULL,
+    sender_id TEXT NOT NULL,
+    container_id TEXT NOT NULL,
+    author_kind TEXT NOT NULL DEFAULT 'user',
+    UNIQUE(content, length, time, sender_id, container_id, author_kind)
+);
+
+INSERT INTO
+    new_ngrams (
+        count,
+        content,
+        length,
+        time,
+        sender_id,
+        container_id
+    )
+SELECT
+    count,
+    content,
+    length,
+    time,
+    sender_id,
+    container_id
+FROM
+    ngrams;
+
+DROP TABLE ngrams;
+
+ALTER TABLE
+    new_ngrams RENAME TO ngrams;
@@ -0,0 +1,6 @@
+CREATE TABLE backfill_cursors (
+    container_id TEXT NOT NULL UNIQUE PRIMARY KEY,
+    oldest_entry_id TEXT,
+    newest_entry_id TEXT,
+    complete BOOLEAN NOT NULL DEFAULT false
+);
@@ -0,0 +1,30 @@
+CREATE TABLE entries (
+    entry_id TEXT NOT NULL UNIQUE PRIMARY KEY,
+    container_id TEXT NOT NULL,
+    sender_id TEXT NOT NULL,
+    unix_timestamp BIGINT NOT NULL,
+    content TEXT NOT NULL,
+    ngrams_cached BOOLEAN NOT NULL DEFAULT false,
+    parent_entry_id TEXT
+);
+
+CREATE TABLE containers (
+    container_id TEXT NOT NULL,
+    container_parent_id TEXT NOT NULL,
+    UNIQUE(container_id, container_parent_id)
+);
+
+CREATE TABLE ngrams (
+    count INTEGER NOT NULL,
+    content TEXT NOT NULL,
+    length INTEGER NOT NULL,
+    time BIGINT NOT NULL,
+    sender_id TEXT NOT NULL,
+    container_id TEXT NOT NULL,
+    UNIQUE(content, length, time, sender_id, container_id)
+);
+
+CREATE TABLE metadata (
+    key TEXT NOT NULL UNIQUE PRIMARY KEY,
+    value TEXT NOT NULL
+);
@@ -0,0 +1,7 @@
+-- The n-grams are now stored in hourly instead of weekly buckets
+DELETE FROM ngrams;
+
+UPDATE
+    entries
+SET
+    ngrams_cached = false;
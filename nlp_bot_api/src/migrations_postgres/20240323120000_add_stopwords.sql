@@ -0,0 +1,5 @@
+CREATE TABLE stopwords (
+    container_id TEXT NOT NULL,
+    word TEXT NOT NULL,
+    UNIQUE(container_id, word)
+);
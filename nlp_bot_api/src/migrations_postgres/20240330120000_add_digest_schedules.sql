@@ -0,0 +1,6 @@
+CREATE TABLE digest_schedules (
+    container_id TEXT NOT NULL UNIQUE PRIMARY KEY,
+    channel_id TEXT NOT NULL,
+    schedule TEXT NOT NULL,
+    last_run_time BIGINT NOT NULL
+);
@@ -0,0 +1,13 @@
+CREATE TABLE guild_settings (
+    container_id TEXT NOT NULL UNIQUE PRIMARY KEY,
+    default_context TEXT,
+    default_limit BIGINT,
+    stopwords TEXT,
+    locale TEXT
+);
+
+CREATE TABLE excluded_containers (
+    container_id TEXT NOT NULL,
+    excluded_container_id TEXT NOT NULL,
+    UNIQUE(container_id, excluded_container_id)
+);
@@ -0,0 +1,3 @@
+CREATE TABLE opted_out_senders (
+    sender_id TEXT NOT NULL UNIQUE PRIMARY KEY
+);
@@ -0,0 +1,18 @@
+-- Entries stored before author kinds were tracked are treated as sent by users
+ALTER TABLE
+    entries
+ADD
+    author_kind TEXT NOT NULL DEFAULT 'user';
+
+ALTER TABLE
+    ngrams
+ADD
+    author_kind TEXT NOT NULL DEFAULT 'user';
+
+ALTER TABLE
+    ngrams DROP CONSTRAINT ngrams_content_length_time_sender_id_container_id_key;
+
+ALTER TABLE
+    ngrams
+ADD
+    UNIQUE(content, length, time, sender_id, container_id, author_kind);
@@ -0,0 +1,3 @@
+-- Hourly buckets multiply the rows of the n-grams, and most queries are
+-- restricted to some containers and often to a time range
+CREATE INDEX ngrams_container_id_time ON ngrams (container_id, time);
@@ -0,0 +1,11 @@
+/// How far the history of a container has been read
+#[derive(Debug, Clone)]
+pub struct BackfillCursor {
+    pub container_id: String,
+    /// The oldest entry read so far
+    pub oldest_entry_id: Option<String>,
+    /// The newest entry read so far
+    pub newest_entry_id: Option<String>,
+    /// Whether the beginning of the container was reached
+    pub complete: bool,
+}
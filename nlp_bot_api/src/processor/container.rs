@@ -1,4 +1,4 @@
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Container {
     pub container_id: String,
     pub container_parent_id: String,
@@ -0,0 +1,27 @@
+use super::entry::SenderForDigestCommand;
+use super::ngram::{NgramForTrendingCommand, NgramSeriesForByContentCommand};
+
+/// When and where the digest of a container is posted
+#[derive(Debug, Clone)]
+pub struct DigestSchedule {
+    pub container_id: String,
+    /// The channel the digest is posted to
+    pub channel_id: String,
+    /// A cron expression evaluated in UTC
+    pub schedule: String,
+    /// When the digest was last posted, or when the schedule was set if it
+    /// has not been posted yet
+    pub last_run_time: i64,
+}
+
+/// A summary of the last week in a container
+pub struct Digest {
+    /// N-grams which did not occur in the baseline at all
+    pub new_ngrams: Vec<NgramForTrendingCommand>,
+    /// N-grams which occurred in the baseline, but grew since
+    pub trending_ngrams: Vec<NgramForTrendingCommand>,
+    pub active_senders: Vec<SenderForDigestCommand>,
+    /// The weekly counts of the top new and trending n-grams over the
+    /// baseline and the last week
+    pub ngram_series: Vec<NgramSeriesForByContentCommand>,
+}
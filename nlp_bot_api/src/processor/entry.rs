@@ -1,42 +1,109 @@
-use super::ngram::{
-    get_ngram_time, get_ngrams_in_word_list, NgramForStore, ALLOWED_NGRAM_CHARACTERS_REGEX,
-    MAX_NGRAM_LENGTH,
-};
+use super::ngram::{get_ngram_time, get_ngrams_in_word_list, NgramForStore, MAX_NGRAM_LENGTH};
+use super::tokenizer::Tokenizer;
+use std::str::FromStr;
 
-#[derive(Debug)]
+/// Who sent an entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub enum AuthorKind {
+    #[default]
+    User,
+    Bot,
+    Webhook,
+    /// Messages like joins and pins, which are attributed to a user
+    System,
+}
+
+impl FromStr for AuthorKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "user" => Ok(Self::User),
+            "bot" => Ok(Self::Bot),
+            "webhook" => Ok(Self::Webhook),
+            "system" => Ok(Self::System),
+            _ => Err(String::from("Invalid author kind")),
+        }
+    }
+}
+
+impl AuthorKind {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Bot => "bot",
+            Self::Webhook => "webhook",
+            Self::System => "system",
+        }
+    }
+}
+
+/// Decides which entries are stored and counted. Senders in
+/// `allowed_sender_ids` are allowed even if their kind is ignored.
+#[derive(Debug, Clone, Default)]
+pub struct AuthorPolicy {
+    pub ignored_author_kinds: Vec<AuthorKind>,
+    pub allowed_sender_ids: Vec<String>,
+}
+
+impl AuthorPolicy {
+    pub fn allows(&self, author_kind: AuthorKind, sender_id: &str) -> bool {
+        !self.ignored_author_kinds.contains(&author_kind)
+            || self.allowed_sender_ids.iter().any(|id| id == sender_id)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Entry {
     pub entry_id: String,
     pub container_id: String,
     pub sender_id: String,
     pub unix_timestamp: i64,
     pub content: String,
+    /// The entry this entry is a reply to
+    pub parent_entry_id: Option<String>,
+    pub author_kind: AuthorKind,
+}
+
+/// How many times a sender replied to another sender
+pub struct ReplyPairForRepliesCommand {
+    pub sender_id: String,
+    pub parent_sender_id: String,
+    pub count: u32,
+}
+
+/// How many entries a sender sent
+pub struct SenderForDigestCommand {
+    pub sender_id: String,
+    pub count: u32,
 }
 
 impl Entry {
-    pub fn get_ngrams(&self) -> Vec<NgramForStore> {
-        let lower_case_content = self.content.to_lowercase();
-        let words: Vec<String> = ALLOWED_NGRAM_CHARACTERS_REGEX
-            .find_iter(&lower_case_content)
-            .map(|mat| mat.as_str().to_string())
-            .collect();
+    pub fn get_ngrams(&self, tokenizer: &dyn Tokenizer) -> Vec<NgramForStore> {
+        let words = tokenizer.tokenize(&self.content);
 
         get_ngrams_in_word_list(words.as_slice(), MAX_NGRAM_LENGTH)
             .iter()
             .map(|words| NgramForStore {
+                entry_id: self.entry_id.clone(),
                 content: words.join(" "),
                 #[allow(clippy::cast_possible_truncation)]
                 length: words.len() as u32,
                 time: get_ngram_time(self.unix_timestamp),
                 container_id: self.container_id.clone(),
                 sender_id: self.sender_id.clone(),
+                author_kind: self.author_kind,
             })
             .collect()
     }
 
-    pub fn get_ngrams_from_entries_slice(entries: &[Self]) -> Vec<NgramForStore> {
+    pub fn get_ngrams_from_entries_slice(
+        entries: &[Self],
+        tokenizer: &dyn Tokenizer,
+    ) -> Vec<NgramForStore> {
         let mut ngrams = Vec::new();
         for entry in entries {
-            ngrams.extend(entry.get_ngrams());
+            ngrams.extend(entry.get_ngrams(tokenizer));
         }
         ngrams
     }
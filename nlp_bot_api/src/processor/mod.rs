@@ -1,15 +1,59 @@
+pub mod backfill;
 pub mod container;
+pub mod digest;
 pub mod entry;
 pub mod ngram;
+pub mod settings;
+pub mod statistics;
+pub mod stopwords;
+pub mod tokenizer;
 
-use self::ngram::{NgramForByCountCommand, NgramsForByContentCommand};
+use self::backfill::BackfillCursor;
+use self::digest::{Digest, DigestSchedule};
+use self::entry::{AuthorPolicy, ReplyPairForRepliesCommand};
+use self::ngram::{
+    Granularity, NgramForByCountCommand, NgramForCollocationsCommand, NgramForDistinctiveCommand,
+    NgramForTrendingCommand, NgramSeriesForByContentCommand, NgramValueForByContentCommand,
+    NgramsForByContentCommand,
+};
+use self::settings::GuildSettings;
+use self::stopwords::{consists_of_stopwords, Language};
+use self::tokenizer::Tokenizer;
 use crate::store::{
-    self,
-    filters::{NgramsByContentFilter, NgramsByCountFilter, Order},
+    filters::{
+        ActiveSendersFilter, NgramTotalCountFilter, NgramsByContentFilter, NgramsByCountFilter,
+        Order, RepliesFilter,
+    },
+    Store,
 };
+use chrono::Utc;
+use core::cmp::Ordering;
 use core::fmt;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use tokio::sync::RwLock;
 
 const ENTRY_LIMIT: u32 = 1000;
+const TOKENIZER_METADATA_KEY: &str = "tokenizer";
+/// Normalised n-gram counts are expressed per this many tokens
+const TOKENS_PER_NORMALISED_COUNT: f64 = 1000.0;
+/// The number of the most frequent n-grams of the target considered when
+/// looking for distinctive n-grams
+const DISTINCTIVE_CANDIDATE_LIMIT: u32 = 1000;
+/// The number of the most frequent n-grams considered when looking for
+/// collocations
+const COLLOCATION_CANDIDATE_LIMIT: u32 = 5000;
+pub const DEFAULT_COLLOCATION_MIN_COUNT: u32 = 5;
+/// The number of the most frequent recent n-grams considered when looking for
+/// trending n-grams
+const TRENDING_CANDIDATE_LIMIT: u32 = 5000;
+pub const DEFAULT_TRENDING_WEEKS: u32 = 1;
+pub const DEFAULT_TRENDING_BASELINE_WEEKS: u32 = 12;
+pub const DEFAULT_TRENDING_MIN_COUNT: u32 = 3;
+/// The number of n-grams and senders in each list of a digest
+const DIGEST_LIST_LIMIT: usize = 5;
+/// The number of the top new and trending n-grams charted in a digest
+const DIGEST_NGRAMS_IN_CHART: usize = 3;
 
 #[derive(Debug)]
 pub enum Error {
@@ -30,14 +74,59 @@ impl fmt::Display for Error {
     }
 }
 
-pub struct Processor {
-    store: store::Sql,
+fn get_normalised_count(count: u32, token_count: u32) -> f64 {
+    if token_count == 0 {
+        return 0.0;
+    }
+
+    f64::from(count) * TOKENS_PER_NORMALISED_COUNT / f64::from(token_count)
+}
+
+pub struct Processor<S: Store + ?Sized = dyn Store> {
+    store: Box<S>,
+    tokenizer: Box<dyn Tokenizer>,
+    author_policy: AuthorPolicy,
+    /// Loaded on first use and dropped whenever a sender opts out or in
+    opted_out_sender_ids: RwLock<Option<HashSet<String>>>,
 }
 
-impl Processor {
+impl<S: Store + ?Sized> Processor<S> {
     #[allow(clippy::missing_const_for_fn)]
-    pub fn new(store: store::Sql) -> Self {
-        Self { store }
+    pub fn new(store: Box<S>, tokenizer: Box<dyn Tokenizer>) -> Self {
+        Self {
+            store,
+            tokenizer,
+            author_policy: AuthorPolicy::default(),
+            opted_out_sender_ids: RwLock::new(None),
+        }
+    }
+
+    /// Entries the policy doesn't allow are not stored, and the ones stored
+    /// before the policy changed are not counted.
+    #[must_use]
+    pub fn with_author_policy(mut self, author_policy: AuthorPolicy) -> Self {
+        self.author_policy = author_policy;
+        self
+    }
+
+    /// If the n-grams were cached using a different tokenizer, this drops them,
+    /// so that they get re-cached by [`Self::cache_ngrams`].
+    pub async fn reset_ngrams_if_tokenizer_changed(&self) -> Result<(), Error> {
+        let stored_tokenizer_name = self.store.get_metadata(TOKENIZER_METADATA_KEY).await?;
+        if stored_tokenizer_name.as_deref() == Some(self.tokenizer.name()) {
+            return Ok(());
+        }
+
+        log::info!(
+            "Tokenizer changed to {} - resetting ngrams...",
+            self.tokenizer.name()
+        );
+        self.store.reset_ngrams().await?;
+        self.store
+            .set_metadata(TOKENIZER_METADATA_KEY, self.tokenizer.name())
+            .await?;
+
+        Ok(())
     }
 
     pub async fn cache_ngrams(&self) -> Result<(), Error> {
@@ -54,35 +143,316 @@ impl Processor {
             };
 
             let entry_ids: Vec<String> = entries.iter().map(|m| m.entry_id.clone()).collect();
-            let ngrams = entry::Entry::get_ngrams_from_entries_slice(entries.as_slice());
+            let ngrams = entry::Entry::get_ngrams_from_entries_slice(
+                entries.as_slice(),
+                self.tokenizer.as_ref(),
+            );
 
-            self.store.add_ngrams(ngrams.as_slice()).await?;
-            self.store.mark_entry_as_ngrams_cached(&entry_ids).await?;
+            self.store.add_ngrams(ngrams.as_slice(), &entry_ids).await?;
         }
 
         log::info!("Cached ngrams for all entries.");
         Ok(())
     }
 
+    /// Drops all n-grams and recomputes them from the stored entries.
+    pub async fn rebuild_ngrams(&self) -> Result<(), Error> {
+        log::info!("Rebuilding ngrams...");
+        self.store.reset_ngrams().await?;
+        self.cache_ngrams().await
+    }
+
     pub async fn add_entry(&self, entry: entry::Entry) -> Result<(), Error> {
-        self.add_entries([entry].as_slice()).await
+        self.add_entries(std::slice::from_ref(&entry)).await
     }
 
+    /// Entries of senders who opted out or which the author policy doesn't
+    /// allow are not stored. Stored entries whose author kind differs, e.g.
+    /// ones stored before it was known, are reclassified.
     pub async fn add_entries(&self, entries: &[entry::Entry]) -> Result<(), Error> {
-        let ngrams = entry::Entry::get_ngrams_from_entries_slice(entries);
+        let entry_ids: Vec<String> = entries.iter().map(|entry| entry.entry_id.clone()).collect();
+        let stored_author_kinds: HashMap<String, entry::AuthorKind> = self
+            .store
+            .get_entries_with_cached_ngrams(&entry_ids)
+            .await?
+            .into_iter()
+            .map(|entry| (entry.entry_id, entry.author_kind))
+            .collect();
+        let (reclassified_entries, entries): (Vec<entry::Entry>, Vec<entry::Entry>) =
+            entries.iter().cloned().partition(|entry| {
+                stored_author_kinds
+                    .get(&entry.entry_id)
+                    .map_or(false, |author_kind| *author_kind != entry.author_kind)
+            });
+        self.replace_entries(&reclassified_entries).await?;
+
+        let entries = self.filter_allowed_entries(&entries).await?;
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        let ngrams = entry::Entry::get_ngrams_from_entries_slice(
+            entries.as_slice(),
+            self.tokenizer.as_ref(),
+        );
 
-        self.store.add_ngrams(ngrams.as_slice()).await?;
-        self.store.add_entries(entries, true).await?;
+        self.store
+            .add_entries(entries.as_slice(), ngrams.as_slice())
+            .await?;
 
         Ok(())
     }
 
+    async fn filter_allowed_entries(
+        &self,
+        entries: &[entry::Entry],
+    ) -> Result<Vec<entry::Entry>, Error> {
+        let opted_out_sender_ids = self.get_opted_out_sender_ids().await?;
+
+        Ok(entries
+            .iter()
+            .filter(|entry| {
+                !opted_out_sender_ids.contains(&entry.sender_id)
+                    && self
+                        .author_policy
+                        .allows(entry.author_kind, &entry.sender_id)
+            })
+            .cloned()
+            .collect())
+    }
+
+    /// Replaces a stored entry (e.g. after its message was edited), keeping
+    /// the n-gram counts in sync. If the entry is no longer allowed, it is
+    /// deleted.
+    pub async fn update_entry(&self, entry: entry::Entry) -> Result<(), Error> {
+        self.replace_entries(std::slice::from_ref(&entry)).await
+    }
+
+    /// Entries which are not stored yet are added and the ones which are no
+    /// longer allowed are deleted.
+    async fn replace_entries(&self, entries: &[entry::Entry]) -> Result<(), Error> {
+        let entries_to_store = self.filter_allowed_entries(entries).await?;
+        let entry_ids_to_store: Vec<String> = entries_to_store
+            .iter()
+            .map(|entry| entry.entry_id.clone())
+            .collect();
+        let entry_ids_to_delete: Vec<String> = entries
+            .iter()
+            .map(|entry| entry.entry_id.clone())
+            .filter(|entry_id| !entry_ids_to_store.contains(entry_id))
+            .collect();
+        if !entry_ids_to_delete.is_empty() {
+            self.delete_entries(&entry_ids_to_delete).await?;
+        }
+        if entries_to_store.is_empty() {
+            return Ok(());
+        }
+
+        let old_entries = self
+            .store
+            .get_entries_with_cached_ngrams(&entry_ids_to_store)
+            .await?;
+        let old_ngrams = entry::Entry::get_ngrams_from_entries_slice(
+            old_entries.as_slice(),
+            self.tokenizer.as_ref(),
+        );
+        let ngrams = entry::Entry::get_ngrams_from_entries_slice(
+            entries_to_store.as_slice(),
+            self.tokenizer.as_ref(),
+        );
+
+        self.store
+            .replace_entries(
+                entries_to_store.as_slice(),
+                old_ngrams.as_slice(),
+                ngrams.as_slice(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_entry(&self, entry_id: &str) -> Result<(), Error> {
+        self.delete_entries(&[entry_id.to_string()]).await
+    }
+
+    /// Deletes the entries and subtracts their n-grams from the stored counts.
+    /// Entries which are not stored are ignored.
+    pub async fn delete_entries(&self, entry_ids: &[String]) -> Result<(), Error> {
+        let entries = self.store.get_entries_with_cached_ngrams(entry_ids).await?;
+        let ngrams = entry::Entry::get_ngrams_from_entries_slice(
+            entries.as_slice(),
+            self.tokenizer.as_ref(),
+        );
+
+        self.store
+            .delete_entries(entry_ids, ngrams.as_slice())
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_opted_out_sender_ids(&self) -> Result<HashSet<String>, Error> {
+        if let Some(opted_out_sender_ids) = self.opted_out_sender_ids.read().await.as_ref() {
+            return Ok(opted_out_sender_ids.clone());
+        }
+
+        // Holding the lock while loading keeps an opt-out which happens
+        // meanwhile from being overwritten by the stale set
+        let mut cached_opted_out_sender_ids = self.opted_out_sender_ids.write().await;
+        if let Some(opted_out_sender_ids) = cached_opted_out_sender_ids.as_ref() {
+            return Ok(opted_out_sender_ids.clone());
+        }
+        let opted_out_sender_ids: HashSet<String> = self
+            .store
+            .get_opted_out_sender_ids()
+            .await?
+            .into_iter()
+            .collect();
+        *cached_opted_out_sender_ids = Some(opted_out_sender_ids.clone());
+        drop(cached_opted_out_sender_ids);
+
+        Ok(opted_out_sender_ids)
+    }
+
+    pub async fn is_sender_opted_out(&self, sender_id: &str) -> Result<bool, Error> {
+        let opted_out_sender_ids = self.get_opted_out_sender_ids().await?;
+
+        Ok(opted_out_sender_ids.contains(sender_id))
+    }
+
+    /// Entries of the sender are no longer stored. Already stored entries are
+    /// kept, see [`Self::forget_sender`].
+    pub async fn opt_out_sender(&self, sender_id: &str) -> Result<(), Error> {
+        self.store.add_opted_out_sender_id(sender_id).await?;
+        *self.opted_out_sender_ids.write().await = None;
+
+        Ok(())
+    }
+
+    pub async fn opt_in_sender(&self, sender_id: &str) -> Result<(), Error> {
+        self.store.remove_opted_out_sender_id(sender_id).await?;
+        *self.opted_out_sender_ids.write().await = None;
+
+        Ok(())
+    }
+
+    /// Deletes all entries of the sender and subtracts their n-grams from the
+    /// stored counts. Returns the number of deleted entries.
+    pub async fn forget_sender(&self, sender_id: &str) -> Result<usize, Error> {
+        let entry_ids = self.store.get_entry_ids_by_sender(sender_id).await?;
+        self.delete_entries_in_chunks(&entry_ids).await?;
+
+        Ok(entry_ids.len())
+    }
+
+    /// Deletes all entries in the container and its children and subtracts
+    /// their n-grams from the stored counts. Returns the number of deleted
+    /// entries.
+    pub async fn forget_container(&self, container_id: &str) -> Result<usize, Error> {
+        let container_ids = self
+            .expand_container_ids_with_children(&[container_id.to_string()])
+            .await?;
+
+        let mut deleted_count = 0;
+        for container_id in container_ids {
+            let entry_ids = self.store.get_entry_ids_in_container(&container_id).await?;
+            self.delete_entries_in_chunks(&entry_ids).await?;
+            deleted_count += entry_ids.len();
+
+            // If the container is read again, it is read from scratch
+            self.store.remove_backfill_cursor(&container_id).await?;
+        }
+
+        Ok(deleted_count)
+    }
+
+    /// Removes the container from the hierarchy, so that it is left out of
+    /// the queries over its parent. Its entries and children are kept, e.g.
+    /// for when a guild is joined again. `forget_container` deletes them.
+    pub async fn remove_container(&self, container_id: &str) -> Result<(), Error> {
+        self.store.remove_container(container_id).await?;
+
+        Ok(())
+    }
+
+    async fn delete_entries_in_chunks(&self, entry_ids: &[String]) -> Result<(), Error> {
+        for entry_ids_chunk in entry_ids.chunks(ENTRY_LIMIT as usize) {
+            self.delete_entries(entry_ids_chunk).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Containers which are already in the hierarchy are left under their
+    /// parent.
     pub async fn add_container(&self, container: &container::Container) -> Result<(), Error> {
         self.store.add_container(container).await?;
 
         Ok(())
     }
 
+    /// Moves the container under its parent, e.g. when a channel is moved to
+    /// another category. Its children move with it.
+    pub async fn set_container_parent(
+        &self,
+        container: &container::Container,
+    ) -> Result<(), Error> {
+        self.store.set_container_parent(container).await?;
+
+        Ok(())
+    }
+
+    /// Children excluded from the container itself are left out.
+    pub async fn get_child_containers(
+        &self,
+        container_id: &str,
+    ) -> Result<Vec<container::Container>, Error> {
+        let excluded_container_ids = self.store.get_excluded_container_ids(container_id).await?;
+
+        Ok(self
+            .store
+            .get_child_container_ids(container_id)
+            .await?
+            .into_iter()
+            .filter(|child_container_id| !excluded_container_ids.contains(child_container_id))
+            .map(|child_container_id| container::Container {
+                container_id: child_container_id,
+                container_parent_id: container_id.to_string(),
+            })
+            .collect())
+    }
+
+    /// Containers read before cursors were stored continue from their oldest
+    /// and newest stored entries.
+    pub async fn get_backfill_cursor(&self, container_id: &str) -> Result<BackfillCursor, Error> {
+        if let Some(cursor) = self.store.get_backfill_cursor(container_id).await? {
+            return Ok(cursor);
+        }
+
+        let (oldest_entry_id, newest_entry_id) = match self
+            .get_first_and_last_entry_id_in_container(container_id)
+            .await
+        {
+            Ok((first_entry_id, last_entry_id)) => (Some(first_entry_id), Some(last_entry_id)),
+            Err(Error::DatabaseError(sqlx::Error::RowNotFound)) => (None, None),
+            Err(e) => return Err(e),
+        };
+
+        Ok(BackfillCursor {
+            container_id: container_id.to_string(),
+            oldest_entry_id,
+            newest_entry_id,
+            complete: false,
+        })
+    }
+
+    pub async fn set_backfill_cursor(&self, cursor: &BackfillCursor) -> Result<(), Error> {
+        self.store.set_backfill_cursor(cursor).await?;
+
+        Ok(())
+    }
+
     pub async fn get_first_and_last_entry_id_in_container(
         &self,
         container_id: &str,
@@ -111,27 +481,43 @@ impl Processor {
         Ok(first_entry_id)
     }
 
+    /// Returns all descendants of the container. Containers excluded from a
+    /// container are left out at any depth below it, along with their
+    /// descendants.
     async fn get_child_container_ids(&self, container_id: &str) -> Result<Vec<String>, Error> {
         let mut container_ids = Vec::new();
-        let mut container_ids_to_explore = vec![container_id.to_string()];
+        // Each container is explored with the exclusions of its ancestors
+        let mut containers_to_explore = vec![(container_id.to_string(), HashSet::new())];
 
         loop {
-            if container_ids_to_explore.is_empty() {
+            if containers_to_explore.is_empty() {
                 break;
             }
 
-            let mut new_container_ids_to_explore = Vec::new();
-            for container_id_to_explore in container_ids_to_explore {
-                let child_container_ids = self
+            let mut new_containers_to_explore = Vec::new();
+            for (container_id_to_explore, mut excluded_container_ids) in containers_to_explore {
+                excluded_container_ids.extend(
+                    self.store
+                        .get_excluded_container_ids(&container_id_to_explore)
+                        .await?,
+                );
+                let child_container_ids: Vec<String> = self
                     .store
                     .get_child_container_ids(&container_id_to_explore)
-                    .await?;
+                    .await?
+                    .into_iter()
+                    .filter(|child_container_id| {
+                        !excluded_container_ids.contains(child_container_id)
+                    })
+                    .collect();
 
                 container_ids.extend(child_container_ids.clone());
-                new_container_ids_to_explore.extend(child_container_ids);
+                new_containers_to_explore.extend(child_container_ids.into_iter().map(
+                    |child_container_id| (child_container_id, excluded_container_ids.clone()),
+                ));
             }
 
-            container_ids_to_explore = new_container_ids_to_explore;
+            containers_to_explore = new_containers_to_explore;
         }
 
         Ok(container_ids)
@@ -149,34 +535,55 @@ impl Processor {
         Ok(new_container_ids)
     }
 
+    /// Counts in the container and its children.
     pub async fn get_ngram_count_in_container(&self, container_id: &str) -> Result<i32, Error> {
-        let child_container_ids = self.get_child_container_ids(container_id).await?;
+        let container_ids = self
+            .expand_container_ids_with_children(&[container_id.to_string()])
+            .await?;
 
         let mut ngram_count = 0;
-        for child_container_id in child_container_ids {
+        for container_id in container_ids {
             ngram_count += self
                 .store
-                .get_ngram_count_in_container(&child_container_id)
+                .get_ngram_count_in_container(&container_id)
                 .await?;
         }
 
         Ok(ngram_count)
     }
 
+    /// Returns how many times n-grams occurred in the container and its
+    /// children. `get_ngram_count_in_container` counts the stored n-grams
+    /// instead, which are summed per sender, container and hour.
+    pub async fn get_ngram_occurrences_in_container(
+        &self,
+        container_id: &str,
+    ) -> Result<u64, Error> {
+        let container_ids = self
+            .expand_container_ids_with_children(&[container_id.to_string()])
+            .await?;
+
+        self.get_ngram_total_count(None, None, container_ids).await
+    }
+
+    /// Counts in the container and its children.
     pub async fn get_entries_count_in_container(&self, container_id: &str) -> Result<i32, Error> {
-        let child_container_ids = self.get_child_container_ids(container_id).await?;
+        let container_ids = self
+            .expand_container_ids_with_children(&[container_id.to_string()])
+            .await?;
 
         let mut entries_count = 0;
-        for child_container_id in child_container_ids {
+        for container_id in container_ids {
             entries_count += self
                 .store
-                .get_entries_count_in_container(&child_container_id)
+                .get_entries_count_in_container(&container_id)
                 .await?;
         }
 
         Ok(entries_count)
     }
 
+    /// N-grams consisting solely of `stopwords` are excluded.
     pub async fn get_ngrams_by_count(
         &self,
         sender_id: Option<String>,
@@ -184,44 +591,675 @@ impl Processor {
         limit: Option<u32>,
         container_ids: &[String],
         order: Option<Order>,
+        stopwords: &[String],
     ) -> Result<Vec<NgramForByCountCommand>, Error> {
         let expanded_container_ids = self
             .expand_container_ids_with_children(container_ids)
             .await?;
 
-        let ngram_filter = NgramsByCountFilter {
+        let mut ngram_filter = NgramsByCountFilter {
             sender_id,
             length,
-            limit: limit.unwrap_or(NgramsByCountFilter::default().limit),
-            order: order.unwrap_or(NgramsByCountFilter::default().order),
+            limit: limit.unwrap_or_else(|| NgramsByCountFilter::default().limit),
+            order: order.unwrap_or_else(|| NgramsByCountFilter::default().order),
             container_ids: expanded_container_ids,
+            stopwords: stopwords.to_vec(),
+            author_policy: self.author_policy.clone(),
+            ..NgramsByCountFilter::default()
         };
+        let stopwords: HashSet<&str> = stopwords.iter().map(String::as_str).collect();
+
+        // The store only excludes single stop-words, so longer n-grams
+        // consisting of stop-words are filtered out here, page by page
+        let mut ngrams = Vec::new();
+        loop {
+            let page = self.store.get_ngrams_by_count(&ngram_filter).await?;
+            let page_length = page.len();
+
+            ngrams.extend(
+                page.into_iter()
+                    .filter(|ngram| !consists_of_stopwords(&ngram.content, &stopwords)),
+            );
+
+            if page_length < ngram_filter.limit as usize
+                || ngrams.len() >= ngram_filter.limit as usize
+            {
+                break;
+            }
+            ngram_filter.offset += ngram_filter.limit;
+        }
+        ngrams.truncate(ngram_filter.limit as usize);
 
-        let ngrams = self.store.get_ngrams_by_count(&ngram_filter).await?;
         Ok(ngrams)
     }
 
+    /// Scores the n-grams sent by the sender in the containers (the target)
+    /// against the n-grams sent by anyone in the background containers, which
+    /// should include the target. Only n-grams used more often in the target
+    /// than in the rest of the background are returned, ordered by their
+    /// log-likelihood score.
+    pub async fn get_distinctive_ngrams(
+        &self,
+        sender_id: Option<String>,
+        length: Option<u32>,
+        limit: Option<u32>,
+        container_ids: &[String],
+        background_container_ids: &[String],
+    ) -> Result<Vec<NgramForDistinctiveCommand>, Error> {
+        let expanded_container_ids = self
+            .expand_container_ids_with_children(container_ids)
+            .await?;
+        let expanded_background_container_ids = self
+            .expand_container_ids_with_children(background_container_ids)
+            .await?;
+
+        let target_ngrams = self
+            .store
+            .get_ngrams_by_count(&NgramsByCountFilter {
+                sender_id: sender_id.clone(),
+                length,
+                container_ids: expanded_container_ids.clone(),
+                limit: DISTINCTIVE_CANDIDATE_LIMIT,
+                author_policy: self.author_policy.clone(),
+                ..NgramsByCountFilter::default()
+            })
+            .await?;
+        if target_ngrams.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let background_counts: HashMap<String, u32> = self
+            .store
+            .get_ngrams_by_count(&NgramsByCountFilter {
+                length,
+                container_ids: expanded_background_container_ids.clone(),
+                contents: target_ngrams
+                    .iter()
+                    .map(|ngram| ngram.content.clone())
+                    .collect(),
+                limit: DISTINCTIVE_CANDIDATE_LIMIT,
+                author_policy: self.author_policy.clone(),
+                ..NgramsByCountFilter::default()
+            })
+            .await?
+            .into_iter()
+            .map(|ngram| (ngram.content, ngram.count))
+            .collect();
+
+        let target_total = self
+            .get_ngram_total_count(sender_id, length, expanded_container_ids)
+            .await?;
+        let background_total = self
+            .get_ngram_total_count(None, length, expanded_background_container_ids)
+            .await?;
+        let rest_total = background_total.saturating_sub(target_total);
+
+        let mut ngrams: Vec<NgramForDistinctiveCommand> = target_ngrams
+            .into_iter()
+            .filter_map(|ngram| {
+                let rest_count = background_counts
+                    .get(&ngram.content)
+                    .copied()
+                    .unwrap_or(0)
+                    .saturating_sub(ngram.count);
+                if !statistics::is_overused(ngram.count, target_total, rest_count, rest_total) {
+                    return None;
+                }
+
+                Some(NgramForDistinctiveCommand {
+                    score: statistics::log_likelihood(
+                        ngram.count,
+                        target_total,
+                        rest_count,
+                        rest_total,
+                    ),
+                    content: ngram.content,
+                    count: ngram.count,
+                    background_count: rest_count,
+                })
+            })
+            .collect();
+        ngrams.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal));
+        ngrams.truncate(limit.unwrap_or_else(|| NgramsByCountFilter::default().limit) as usize);
+
+        Ok(ngrams)
+    }
+
+    /// Scores the n-grams of the given length (2 by default) occurring at least
+    /// `min_count` times by the pointwise mutual information of their words.
+    pub async fn get_collocations(
+        &self,
+        length: Option<u32>,
+        min_count: Option<u32>,
+        limit: Option<u32>,
+        container_ids: &[String],
+    ) -> Result<Vec<NgramForCollocationsCommand>, Error> {
+        let length = length.unwrap_or(2);
+        let min_count = min_count.unwrap_or(DEFAULT_COLLOCATION_MIN_COUNT);
+        let expanded_container_ids = self
+            .expand_container_ids_with_children(container_ids)
+            .await?;
+
+        let candidate_ngrams: Vec<NgramForByCountCommand> = self
+            .store
+            .get_ngrams_by_count(&NgramsByCountFilter {
+                length: Some(length),
+                container_ids: expanded_container_ids.clone(),
+                limit: COLLOCATION_CANDIDATE_LIMIT,
+                author_policy: self.author_policy.clone(),
+                ..NgramsByCountFilter::default()
+            })
+            .await?
+            .into_iter()
+            .filter(|ngram| ngram.count >= min_count)
+            .collect();
+        if candidate_ngrams.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let words: HashSet<&str> = candidate_ngrams
+            .iter()
+            .flat_map(|ngram| ngram.content.split(' '))
+            .collect();
+        let word_counts: HashMap<String, u32> = self
+            .store
+            .get_ngrams_by_count(&NgramsByCountFilter {
+                length: Some(1),
+                container_ids: expanded_container_ids.clone(),
+                contents: words.iter().map(|word| String::from(*word)).collect(),
+                limit: u32::try_from(words.len()).unwrap_or(u32::MAX),
+                author_policy: self.author_policy.clone(),
+                ..NgramsByCountFilter::default()
+            })
+            .await?
+            .into_iter()
+            .map(|ngram| (ngram.content, ngram.count))
+            .collect();
+
+        let total = self
+            .get_ngram_total_count(None, Some(length), expanded_container_ids.clone())
+            .await?;
+        let word_total = self
+            .get_ngram_total_count(None, Some(1), expanded_container_ids)
+            .await?;
+
+        let mut ngrams: Vec<NgramForCollocationsCommand> = candidate_ngrams
+            .into_iter()
+            .filter_map(|ngram| {
+                let ngram_word_counts: Vec<u32> = ngram
+                    .content
+                    .split(' ')
+                    .map(|word| word_counts.get(word).copied().unwrap_or(0))
+                    .collect();
+                // This can happen if the words are not counted as unigrams
+                if ngram_word_counts.contains(&0) {
+                    return None;
+                }
+
+                Some(NgramForCollocationsCommand {
+                    score: statistics::pointwise_mutual_information(
+                        ngram.count,
+                        total,
+                        &ngram_word_counts,
+                        word_total,
+                    ),
+                    content: ngram.content,
+                    count: ngram.count,
+                })
+            })
+            .collect();
+        ngrams.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal));
+        ngrams.truncate(limit.unwrap_or_else(|| NgramsByCountFilter::default().limit) as usize);
+
+        Ok(ngrams)
+    }
+
+    /// Compares the n-grams of the last `weeks` weeks to the `baseline_weeks`
+    /// weeks before them. N-grams occurring at least `min_count` times recently
+    /// and more often than in the baseline are returned, ordered by growth.
+    pub async fn get_trending_ngrams(
+        &self,
+        length: Option<u32>,
+        limit: Option<u32>,
+        container_ids: &[String],
+        weeks: Option<u32>,
+        baseline_weeks: Option<u32>,
+        min_count: Option<u32>,
+    ) -> Result<Vec<NgramForTrendingCommand>, Error> {
+        let weeks = weeks.unwrap_or(DEFAULT_TRENDING_WEEKS).max(1);
+        let baseline_weeks = baseline_weeks
+            .unwrap_or(DEFAULT_TRENDING_BASELINE_WEEKS)
+            .max(1);
+        let min_count = min_count.unwrap_or(DEFAULT_TRENDING_MIN_COUNT);
+        let expanded_container_ids = self
+            .expand_container_ids_with_children(container_ids)
+            .await?;
+
+        let now = ngram::get_ngram_time(Utc::now().timestamp());
+        let recent_start = now - i64::from(weeks) * ngram::SECONDS_IN_WEEK;
+        let baseline_start = recent_start - i64::from(baseline_weeks) * ngram::SECONDS_IN_WEEK;
+
+        let recent_ngrams: Vec<NgramForByCountCommand> = self
+            .store
+            .get_ngrams_by_count(&NgramsByCountFilter {
+                length,
+                container_ids: expanded_container_ids.clone(),
+                since: Some(recent_start),
+                limit: TRENDING_CANDIDATE_LIMIT,
+                author_policy: self.author_policy.clone(),
+                ..NgramsByCountFilter::default()
+            })
+            .await?
+            .into_iter()
+            .filter(|ngram| ngram.count >= min_count)
+            .collect();
+        if recent_ngrams.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let baseline_counts: HashMap<String, u32> = self
+            .store
+            .get_ngrams_by_count(&NgramsByCountFilter {
+                length,
+                container_ids: expanded_container_ids,
+                contents: recent_ngrams
+                    .iter()
+                    .map(|ngram| ngram.content.clone())
+                    .collect(),
+                since: Some(baseline_start),
+                until: Some(recent_start),
+                limit: TRENDING_CANDIDATE_LIMIT,
+                author_policy: self.author_policy.clone(),
+                ..NgramsByCountFilter::default()
+            })
+            .await?
+            .into_iter()
+            .map(|ngram| (ngram.content, ngram.count))
+            .collect();
+
+        let mut ngrams: Vec<NgramForTrendingCommand> = recent_ngrams
+            .into_iter()
+            .map(|ngram| {
+                let baseline_count = baseline_counts.get(&ngram.content).copied().unwrap_or(0);
+
+                NgramForTrendingCommand {
+                    growth: statistics::growth_factor(
+                        ngram.count,
+                        weeks,
+                        baseline_count,
+                        baseline_weeks,
+                    ),
+                    content: ngram.content,
+                    count: ngram.count,
+                    baseline_count,
+                }
+            })
+            .filter(|ngram| ngram.growth > 1.0)
+            .collect();
+        ngrams.sort_by(|a, b| b.growth.partial_cmp(&a.growth).unwrap_or(Ordering::Equal));
+        ngrams.truncate(limit.unwrap_or_else(|| NgramsByCountFilter::default().limit) as usize);
+
+        Ok(ngrams)
+    }
+
+    /// Returns the total number of n-gram occurrences matching the filter.
+    async fn get_ngram_total_count(
+        &self,
+        sender_id: Option<String>,
+        length: Option<u32>,
+        expanded_container_ids: Vec<String>,
+    ) -> Result<u64, Error> {
+        let total_count = self
+            .store
+            .get_ngram_total_count(&NgramTotalCountFilter {
+                sender_id,
+                length,
+                container_ids: expanded_container_ids,
+                author_policy: self.author_policy.clone(),
+            })
+            .await?;
+
+        Ok(total_count)
+    }
+
+    /// Returns the built-in stop-words of the language along with the custom
+    /// stop-words of the container.
+    pub async fn get_stopwords(
+        &self,
+        language: Option<Language>,
+        container_id: Option<&str>,
+    ) -> Result<Vec<String>, Error> {
+        let mut stopwords = language.map(Language::get_stopwords).unwrap_or_default();
+        if let Some(container_id) = container_id {
+            stopwords.extend(self.store.get_custom_stopwords(container_id).await?);
+        }
+
+        Ok(stopwords)
+    }
+
+    pub async fn get_custom_stopwords(&self, container_id: &str) -> Result<Vec<String>, Error> {
+        let stopwords = self.store.get_custom_stopwords(container_id).await?;
+
+        Ok(stopwords)
+    }
+
+    pub async fn add_custom_stopwords(
+        &self,
+        container_id: &str,
+        words: &[String],
+    ) -> Result<(), Error> {
+        self.store.add_custom_stopwords(container_id, words).await?;
+
+        Ok(())
+    }
+
+    pub async fn remove_custom_stopwords(
+        &self,
+        container_id: &str,
+        words: &[String],
+    ) -> Result<(), Error> {
+        self.store
+            .remove_custom_stopwords(container_id, words)
+            .await?;
+
+        Ok(())
+    }
+
     pub async fn get_ngram_by_content(
         &self,
         content: &str,
         sender_id: Option<String>,
         container_ids: &[String],
+        granularity: Option<Granularity>,
     ) -> Result<Vec<NgramsForByContentCommand>, Error> {
         let expanded_container_ids = self
             .expand_container_ids_with_children(container_ids)
             .await?;
 
+        self.get_ngram_by_content_in_containers(
+            content,
+            sender_id,
+            expanded_container_ids,
+            granularity.unwrap_or_default(),
+        )
+        .await
+    }
+
+    /// Returns a time series for each of the n-grams, in the same order. If
+    /// `normalise` is set, the values are occurrences per 1,000 tokens sent in
+    /// the same bucket, by the same sender and in the same containers.
+    pub async fn get_ngrams_by_content(
+        &self,
+        contents: &[String],
+        sender_id: Option<String>,
+        container_ids: &[String],
+        granularity: Option<Granularity>,
+        normalise: bool,
+    ) -> Result<Vec<NgramSeriesForByContentCommand>, Error> {
+        let granularity = granularity.unwrap_or_default();
+        let expanded_container_ids = self
+            .expand_container_ids_with_children(container_ids)
+            .await?;
+
+        let token_counts: HashMap<i64, u32> = if normalise {
+            self.store
+                .get_ngram_by_content(&NgramsByContentFilter {
+                    content: None,
+                    length: Some(1),
+                    sender_id: sender_id.clone(),
+                    container_ids: expanded_container_ids.clone(),
+                    granularity,
+                    author_policy: self.author_policy.clone(),
+                })
+                .await?
+                .into_iter()
+                .map(|ngram| (ngram.time, ngram.count))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+
+        let mut ngram_series = Vec::new();
+        for content in contents {
+            let ngrams = self
+                .get_ngram_by_content_in_containers(
+                    content,
+                    sender_id.clone(),
+                    expanded_container_ids.clone(),
+                    granularity,
+                )
+                .await?;
+
+            ngram_series.push(NgramSeriesForByContentCommand {
+                content: content.clone(),
+                ngrams: ngrams
+                    .into_iter()
+                    .map(|ngram| NgramValueForByContentCommand {
+                        value: if normalise {
+                            get_normalised_count(
+                                ngram.count,
+                                token_counts.get(&ngram.time).copied().unwrap_or(0),
+                            )
+                        } else {
+                            f64::from(ngram.count)
+                        },
+                        time: ngram.time,
+                    })
+                    .collect(),
+            });
+        }
+
+        Ok(ngram_series)
+    }
+
+    async fn get_ngram_by_content_in_containers(
+        &self,
+        content: &str,
+        sender_id: Option<String>,
+        expanded_container_ids: Vec<String>,
+        granularity: Granularity,
+    ) -> Result<Vec<NgramsForByContentCommand>, Error> {
         let mut ngrams = self
             .store
             .get_ngram_by_content(&NgramsByContentFilter {
-                content: String::from(content),
+                content: Some(String::from(content)),
+                length: None,
                 sender_id,
                 container_ids: expanded_container_ids,
+                granularity,
+                author_policy: self.author_policy.clone(),
             })
             .await?;
 
-        ngram::fill_gaps(ngrams.as_mut());
+        ngram::fill_gaps(ngrams.as_mut(), granularity);
 
         Ok(ngrams)
     }
+
+    pub async fn get_reply_pairs(
+        &self,
+        limit: Option<u32>,
+        container_ids: &[String],
+    ) -> Result<Vec<ReplyPairForRepliesCommand>, Error> {
+        let expanded_container_ids = self
+            .expand_container_ids_with_children(container_ids)
+            .await?;
+
+        let reply_pairs = self
+            .store
+            .get_reply_pairs(&RepliesFilter {
+                container_ids: expanded_container_ids,
+                limit: limit.unwrap_or_else(|| RepliesFilter::default().limit),
+                author_policy: self.author_policy.clone(),
+            })
+            .await?;
+
+        Ok(reply_pairs)
+    }
+
+    pub async fn get_digest_schedules(&self) -> Result<Vec<DigestSchedule>, Error> {
+        let schedules = self.store.get_digest_schedules().await?;
+
+        Ok(schedules)
+    }
+
+    pub async fn get_digest_schedule(
+        &self,
+        container_id: &str,
+    ) -> Result<Option<DigestSchedule>, Error> {
+        let schedules = self.store.get_digest_schedules().await?;
+
+        Ok(schedules
+            .into_iter()
+            .find(|schedule| schedule.container_id == container_id))
+    }
+
+    pub async fn set_digest_schedule(&self, schedule: &DigestSchedule) -> Result<(), Error> {
+        self.store.set_digest_schedule(schedule).await?;
+
+        Ok(())
+    }
+
+    pub async fn remove_digest_schedule(&self, container_id: &str) -> Result<(), Error> {
+        self.store.remove_digest_schedule(container_id).await?;
+
+        Ok(())
+    }
+
+    pub async fn set_digest_last_run_time(
+        &self,
+        container_id: &str,
+        last_run_time: i64,
+    ) -> Result<(), Error> {
+        self.store
+            .set_digest_last_run_time(container_id, last_run_time)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Summarises the last week in the container, comparing it to the default
+    /// trending baseline. N-grams consisting solely of the stop-words set in
+    /// the container's settings and its custom stop-words are left out.
+    pub async fn get_digest(&self, container_id: &str) -> Result<Digest, Error> {
+        let container_ids = vec![container_id.to_string()];
+        let language = self
+            .get_guild_settings(container_id)
+            .await?
+            .stopwords
+            .and_then(|stopwords| Language::from_str(&stopwords).ok());
+        let stopwords = self.get_stopwords(language, Some(container_id)).await?;
+        let stopwords: HashSet<&str> = stopwords.iter().map(String::as_str).collect();
+
+        let (mut new_ngrams, mut trending_ngrams): (Vec<_>, Vec<_>) = self
+            .get_trending_ngrams(
+                None,
+                Some(TRENDING_CANDIDATE_LIMIT),
+                &container_ids,
+                None,
+                None,
+                None,
+            )
+            .await?
+            .into_iter()
+            .filter(|ngram| !consists_of_stopwords(&ngram.content, &stopwords))
+            .partition(|ngram| ngram.baseline_count == 0);
+        new_ngrams.truncate(DIGEST_LIST_LIMIT);
+        trending_ngrams.truncate(DIGEST_LIST_LIMIT);
+
+        let now = Utc::now().timestamp();
+        let active_senders = self
+            .store
+            .get_active_senders(&ActiveSendersFilter {
+                container_ids: self
+                    .expand_container_ids_with_children(&container_ids)
+                    .await?,
+                since: Some(now - i64::from(DEFAULT_TRENDING_WEEKS) * ngram::SECONDS_IN_WEEK),
+                limit: u32::try_from(DIGEST_LIST_LIMIT).unwrap_or(u32::MAX),
+                author_policy: self.author_policy.clone(),
+            })
+            .await?;
+
+        let chart_contents: Vec<String> = new_ngrams
+            .iter()
+            .chain(trending_ngrams.iter())
+            .take(DIGEST_NGRAMS_IN_CHART)
+            .map(|ngram| ngram.content.clone())
+            .collect();
+        let chart_start = Granularity::Week.truncate(
+            now - i64::from(DEFAULT_TRENDING_WEEKS + DEFAULT_TRENDING_BASELINE_WEEKS)
+                * ngram::SECONDS_IN_WEEK,
+        );
+        let mut ngram_series = self
+            .get_ngrams_by_content(
+                &chart_contents,
+                None,
+                &container_ids,
+                Some(Granularity::Week),
+                false,
+            )
+            .await?;
+        for series in &mut ngram_series {
+            series.ngrams.retain(|ngram| ngram.time >= chart_start);
+        }
+
+        Ok(Digest {
+            new_ngrams,
+            trending_ngrams,
+            active_senders,
+            ngram_series,
+        })
+    }
+
+    /// Returns the default settings if the guild has none.
+    pub async fn get_guild_settings(&self, container_id: &str) -> Result<GuildSettings, Error> {
+        let settings = self.store.get_guild_settings(container_id).await?;
+
+        Ok(settings.unwrap_or_else(|| GuildSettings {
+            container_id: container_id.to_string(),
+            ..GuildSettings::default()
+        }))
+    }
+
+    pub async fn set_guild_settings(&self, settings: &GuildSettings) -> Result<(), Error> {
+        self.store.set_guild_settings(settings).await?;
+
+        Ok(())
+    }
+
+    pub async fn get_excluded_container_ids(
+        &self,
+        container_id: &str,
+    ) -> Result<Vec<String>, Error> {
+        let excluded_container_ids = self.store.get_excluded_container_ids(container_id).await?;
+
+        Ok(excluded_container_ids)
+    }
+
+    /// The excluded container and everything below it are left out of the
+    /// queries over the container. It can be at any depth below the
+    /// container, e.g. a category, a channel or a thread in a guild.
+    pub async fn add_excluded_container_id(
+        &self,
+        container_id: &str,
+        excluded_container_id: &str,
+    ) -> Result<(), Error> {
+        self.store
+            .add_excluded_container_id(container_id, excluded_container_id)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn remove_excluded_container_id(
+        &self,
+        container_id: &str,
+        excluded_container_id: &str,
+    ) -> Result<(), Error> {
+        self.store
+            .remove_excluded_container_id(container_id, excluded_container_id)
+            .await?;
+
+        Ok(())
+    }
 }
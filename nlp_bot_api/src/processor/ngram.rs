@@ -1,18 +1,15 @@
-use lazy_static::lazy_static;
-use regex::Regex;
+use super::entry::AuthorKind;
+use chrono::{DateTime, Datelike, NaiveDate};
+use std::str::FromStr;
 
-// TODO: Should we just split on whitespace?
-/// We only allow English, Czech, Spanish and German characters though
-/// contributions to handle more complex situations are welcome!
-const ALLOWED_NGRAM_CHARACTERS: &str = r"[a-z0-9ěščřžýáíéñüßöäňůúó']+";
-const SECONDS_IN_WEEK: i64 = 7 * 24 * 60 * 60;
+const SECONDS_IN_HOUR: i64 = 60 * 60;
+const SECONDS_IN_DAY: i64 = 24 * SECONDS_IN_HOUR;
+pub const SECONDS_IN_WEEK: i64 = 7 * SECONDS_IN_DAY;
+/// No month is longer than this, so adding it to the start of a month always
+/// lands in the next one
+const MAX_SECONDS_IN_MONTH: i64 = 31 * SECONDS_IN_DAY;
 
 pub const MAX_NGRAM_LENGTH: u8 = 5;
-lazy_static! {
-    pub static ref ALLOWED_NGRAM_CHARACTERS_REGEX: Regex = #[allow(clippy::unwrap_used)] // A failing regex would be discovered on first run
-    Regex::new(ALLOWED_NGRAM_CHARACTERS)
-        .unwrap();
-}
 
 fn get_ngram(words: &[String], ngram_length: u8, start_index: usize) -> Option<&[String]> {
     if start_index + ngram_length as usize > words.len() {
@@ -39,51 +36,108 @@ pub fn get_ngrams_in_word_list(words: &[String], max_ngram_length: u8) -> Vec<&[
     ngrams
 }
 
+/// The n-grams are stored in hourly buckets, so that they can be aggregated
+/// into any [`Granularity`] at query time.
 pub const fn get_ngram_time(time: i64) -> i64 {
-    time - (time % SECONDS_IN_WEEK)
+    time - (time % SECONDS_IN_HOUR)
+}
+
+/// The size of the time buckets of an n-gram time series
+#[derive(Debug, Clone, Copy, Default)]
+pub enum Granularity {
+    Hour,
+    Day,
+    #[default]
+    Week,
+    Month,
 }
 
-pub fn fill_gaps(ngrams: &mut Vec<NgramsForByContentCommand>) {
-    if ngrams.len() < 2 {
-        return;
+impl FromStr for Granularity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hour" => Ok(Self::Hour),
+            "day" => Ok(Self::Day),
+            "week" => Ok(Self::Week),
+            "month" => Ok(Self::Month),
+            _ => Err(String::from("Invalid granularity")),
+        }
     }
+}
 
-    for i in 1..(ngrams.len() - 1) {
-        let diff = ngrams[i].time - ngrams[i - 1].time;
-
-        let n = i;
-        if diff > 2 * SECONDS_IN_WEEK {
-            ngrams.splice(
-                n..n,
-                [
-                    NgramsForByContentCommand {
-                        time: ngrams[i - 1].time + SECONDS_IN_WEEK,
-                        count: 0,
-                    },
-                    NgramsForByContentCommand {
-                        time: ngrams[i].time - SECONDS_IN_WEEK,
-                        count: 0,
-                    },
-                ],
-            );
-        } else if diff > SECONDS_IN_WEEK {
-            ngrams.splice(
-                n..n,
-                [NgramsForByContentCommand {
-                    time: ngrams[i - 1].time + SECONDS_IN_WEEK,
+impl Granularity {
+    /// Returns the start of the bucket the time falls into
+    pub fn truncate(self, time: i64) -> i64 {
+        match self {
+            Self::Hour => time - (time % SECONDS_IN_HOUR),
+            Self::Day => time - (time % SECONDS_IN_DAY),
+            Self::Week => time - (time % SECONDS_IN_WEEK),
+            Self::Month => DateTime::from_timestamp(time, 0)
+                .and_then(|date_time| {
+                    let date = date_time.date_naive();
+                    NaiveDate::from_ymd_opt(date.year(), date.month(), 1)
+                })
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+                .map_or(time, |date_time| date_time.and_utc().timestamp()),
+        }
+    }
+
+    /// Returns the start of the bucket after the one starting at `time`
+    pub fn next(self, time: i64) -> i64 {
+        match self {
+            Self::Hour => time + SECONDS_IN_HOUR,
+            Self::Day => time + SECONDS_IN_DAY,
+            Self::Week => time + SECONDS_IN_WEEK,
+            Self::Month => self.truncate(time + MAX_SECONDS_IN_MONTH),
+        }
+    }
+
+    /// Returns the start of the bucket before the one starting at `time`
+    pub fn previous(self, time: i64) -> i64 {
+        self.truncate(time - 1)
+    }
+}
+
+/// Inserts zero counts around gaps in the time series, so that the chart
+/// drops to zero instead of interpolating between distant buckets.
+pub fn fill_gaps(ngrams: &mut Vec<NgramsForByContentCommand>, granularity: Granularity) {
+    let mut filled_ngrams: Vec<NgramsForByContentCommand> = Vec::with_capacity(ngrams.len());
+
+    for ngram in ngrams.drain(..) {
+        if let Some(previous_ngram) = filled_ngrams.last() {
+            let after_previous = granularity.next(previous_ngram.time);
+            let before_current = granularity.previous(ngram.time);
+
+            if after_previous < ngram.time {
+                filled_ngrams.push(NgramsForByContentCommand {
+                    time: after_previous,
+                    count: 0,
+                });
+            }
+            if after_previous < before_current {
+                filled_ngrams.push(NgramsForByContentCommand {
+                    time: before_current,
                     count: 0,
-                }],
-            );
+                });
+            }
         }
+
+        filled_ngrams.push(ngram);
     }
+
+    *ngrams = filled_ngrams;
 }
 
 pub struct NgramForStore {
+    /// The entry the n-gram comes from
+    pub entry_id: String,
     pub content: String,
     pub length: u32,
     pub time: i64,
     pub container_id: String,
     pub sender_id: String,
+    pub author_kind: AuthorKind,
 }
 
 pub struct NgramForByCountCommand {
@@ -91,8 +145,49 @@ pub struct NgramForByCountCommand {
     pub count: u32,
 }
 
+pub struct NgramForCollocationsCommand {
+    pub content: String,
+    pub count: u32,
+    /// The pointwise mutual information
+    pub score: f64,
+}
+
+pub struct NgramForTrendingCommand {
+    pub content: String,
+    /// The number of occurrences in the recent period
+    pub count: u32,
+    /// The number of occurrences in the baseline period
+    pub baseline_count: u32,
+    /// How many times the recent weekly average exceeds the baseline one
+    pub growth: f64,
+}
+
+pub struct NgramForDistinctiveCommand {
+    pub content: String,
+    /// The number of occurrences in the target
+    pub count: u32,
+    /// The number of occurrences in the background outside of the target
+    pub background_count: u32,
+    /// The log-likelihood score
+    pub score: f64,
+}
+
 #[derive(Clone)]
 pub struct NgramsForByContentCommand {
     pub count: u32,
     pub time: i64,
 }
+
+/// A point of an n-gram time series, either the number of occurrences or the
+/// relative frequency
+#[derive(Clone)]
+pub struct NgramValueForByContentCommand {
+    pub value: f64,
+    pub time: i64,
+}
+
+/// The time series of a single n-gram
+pub struct NgramSeriesForByContentCommand {
+    pub content: String,
+    pub ngrams: Vec<NgramValueForByContentCommand>,
+}
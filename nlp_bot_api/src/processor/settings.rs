@@ -0,0 +1,13 @@
+/// The settings of a guild, i.e. a container directly below `discord`. Unset
+/// settings fall back to the defaults of the commands.
+#[derive(Debug, Clone, Default)]
+pub struct GuildSettings {
+    pub container_id: String,
+    /// Either `channel`, `server`, `discord` or `all`
+    pub default_context: Option<String>,
+    pub default_limit: Option<u32>,
+    /// Either a language of the built-in stop-words or `custom`
+    pub stopwords: Option<String>,
+    /// Determines how dates are formatted in charts
+    pub locale: Option<String>,
+}
@@ -0,0 +1,66 @@
+/// Returns whether the n-gram is relatively more frequent in the first corpus
+/// than in the second one.
+pub fn is_overused(count: u32, total: u64, other_count: u32, other_total: u64) -> bool {
+    if total == 0 {
+        return false;
+    }
+    if other_total == 0 {
+        return true;
+    }
+
+    u64::from(count) * other_total > u64::from(other_count) * total
+}
+
+/// Dunning's log-likelihood (G²) of an n-gram occurring `count` times out of
+/// `total` n-grams in one corpus and `other_count` times out of `other_total`
+/// in another.
+#[allow(clippy::cast_precision_loss)]
+pub fn log_likelihood(count: u32, total: u64, other_count: u32, other_total: u64) -> f64 {
+    let count = f64::from(count);
+    let other_count = f64::from(other_count);
+    let total = total as f64;
+    let other_total = other_total as f64;
+
+    let expected = total * (count + other_count) / (total + other_total);
+    let other_expected = other_total * (count + other_count) / (total + other_total);
+
+    2.0 * (get_log_likelihood_term(count, expected)
+        + get_log_likelihood_term(other_count, other_expected))
+}
+
+fn get_log_likelihood_term(observed: f64, expected: f64) -> f64 {
+    if observed == 0.0 || expected == 0.0 {
+        return 0.0;
+    }
+
+    observed * (observed / expected).ln()
+}
+
+/// Pointwise mutual information (in bits) of an n-gram occurring `count` times
+/// out of `total` n-grams of the same length, whose words occur `word_counts`
+/// times out of `word_total` words.
+#[allow(clippy::cast_precision_loss)]
+pub fn pointwise_mutual_information(
+    count: u32,
+    total: u64,
+    word_counts: &[u32],
+    word_total: u64,
+) -> f64 {
+    let probability = f64::from(count) / total as f64;
+    let independent_probability: f64 = word_counts
+        .iter()
+        .map(|word_count| f64::from(*word_count) / word_total as f64)
+        .product();
+
+    (probability / independent_probability).log2()
+}
+
+/// How many times the weekly average of the recent period exceeds the one of
+/// the baseline period. One occurrence is added to the baseline, so that new
+/// n-grams don't have an infinite growth.
+pub fn growth_factor(count: u32, weeks: u32, baseline_count: u32, baseline_weeks: u32) -> f64 {
+    let weekly_average = f64::from(count) / f64::from(weeks);
+    let baseline_weekly_average = (f64::from(baseline_count) + 1.0) / f64::from(baseline_weeks);
+
+    weekly_average / baseline_weekly_average
+}
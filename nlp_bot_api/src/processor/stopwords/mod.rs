@@ -0,0 +1,54 @@
+use std::collections::HashSet;
+use std::hash::BuildHasher;
+use std::str::FromStr;
+
+const ENGLISH_STOPWORDS: &str = include_str!("en.txt");
+const CZECH_STOPWORDS: &str = include_str!("cs.txt");
+const SPANISH_STOPWORDS: &str = include_str!("es.txt");
+const GERMAN_STOPWORDS: &str = include_str!("de.txt");
+
+/// A language with a built-in stop-word list
+#[derive(Debug, Clone, Copy)]
+pub enum Language {
+    English,
+    Czech,
+    Spanish,
+    German,
+}
+
+impl FromStr for Language {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "en" => Ok(Self::English),
+            "cs" => Ok(Self::Czech),
+            "es" => Ok(Self::Spanish),
+            "de" => Ok(Self::German),
+            _ => Err(String::from("Invalid language")),
+        }
+    }
+}
+
+impl Language {
+    pub fn get_stopwords(self) -> Vec<String> {
+        let stopwords = match self {
+            Self::English => ENGLISH_STOPWORDS,
+            Self::Czech => CZECH_STOPWORDS,
+            Self::Spanish => SPANISH_STOPWORDS,
+            Self::German => GERMAN_STOPWORDS,
+        };
+
+        stopwords
+            .lines()
+            .map(str::trim)
+            .filter(|word| !word.is_empty())
+            .map(String::from)
+            .collect()
+    }
+}
+
+/// N-gram words are separated by a single space
+pub fn consists_of_stopwords<S: BuildHasher>(content: &str, stopwords: &HashSet<&str, S>) -> bool {
+    content.split(' ').all(|word| stopwords.contains(word))
+}
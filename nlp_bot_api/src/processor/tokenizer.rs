@@ -0,0 +1,83 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::str::FromStr;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// We only allow English, Czech, Spanish and German characters
+const LEGACY_ALLOWED_CHARACTERS: &str = r"[a-z0-9ěščřžýáíéñüßöäňůúó']+";
+
+lazy_static! {
+    static ref LEGACY_ALLOWED_CHARACTERS_REGEX: Regex = #[allow(clippy::unwrap_used)] // A failing regex would be discovered on first run
+    Regex::new(LEGACY_ALLOWED_CHARACTERS)
+        .unwrap();
+}
+
+/// Splits the content of an entry into the words n-grams are made of
+pub trait Tokenizer: Send + Sync {
+    /// The name under which the tokenizer is stored, so that we know when the
+    /// n-grams need to be re-cached
+    fn name(&self) -> &'static str;
+
+    fn tokenize(&self, content: &str) -> Vec<String>;
+}
+
+/// Splits on word boundaries as per UAX #29
+pub struct UnicodeTokenizer;
+
+impl Tokenizer for UnicodeTokenizer {
+    fn name(&self) -> &'static str {
+        "unicode"
+    }
+
+    fn tokenize(&self, content: &str) -> Vec<String> {
+        content
+            .to_lowercase()
+            .unicode_words()
+            .map(String::from)
+            .collect()
+    }
+}
+
+/// Only accepts a handful of Latin characters
+pub struct LegacyTokenizer;
+
+impl Tokenizer for LegacyTokenizer {
+    fn name(&self) -> &'static str {
+        "legacy"
+    }
+
+    fn tokenize(&self, content: &str) -> Vec<String> {
+        LEGACY_ALLOWED_CHARACTERS_REGEX
+            .find_iter(&content.to_lowercase())
+            .map(|mat| mat.as_str().to_string())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub enum TokenizerKind {
+    #[default]
+    Unicode,
+    Legacy,
+}
+
+impl TokenizerKind {
+    pub fn into_tokenizer(self) -> Box<dyn Tokenizer> {
+        match self {
+            Self::Unicode => Box::new(UnicodeTokenizer),
+            Self::Legacy => Box::new(LegacyTokenizer),
+        }
+    }
+}
+
+impl FromStr for TokenizerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "unicode" => Ok(Self::Unicode),
+            "legacy" => Ok(Self::Legacy),
+            _ => Err(String::from("Invalid tokenizer")),
+        }
+    }
+}
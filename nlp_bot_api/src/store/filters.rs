@@ -1,3 +1,5 @@
+use crate::processor::entry::AuthorPolicy;
+use crate::processor::ngram::Granularity;
 use std::str::FromStr;
 
 #[derive(Debug, Clone)]
@@ -23,8 +25,18 @@ pub struct NgramsByCountFilter {
     pub sender_id: Option<String>,
     pub length: Option<u32>,
     pub container_ids: Vec<String>,
+    /// If not empty, only n-grams with one of these contents are included
+    pub contents: Vec<String>,
+    /// N-grams with exactly this content are excluded
+    pub stopwords: Vec<String>,
+    /// Only n-grams with `time` at or after this are included
+    pub since: Option<i64>,
+    /// Only n-grams with `time` before this are included
+    pub until: Option<i64>,
     pub limit: u32,
+    pub offset: u32,
     pub order: Order,
+    pub author_policy: AuthorPolicy,
 }
 
 impl Default for NgramsByCountFilter {
@@ -33,15 +45,72 @@ impl Default for NgramsByCountFilter {
             sender_id: None,
             length: None,
             container_ids: Vec::new(),
+            contents: Vec::new(),
+            stopwords: Vec::new(),
+            since: None,
+            until: None,
             limit: 10,
+            offset: 0,
             order: Order::Descending,
+            author_policy: AuthorPolicy::default(),
         }
     }
 }
 
+#[derive(Debug)]
+pub struct RepliesFilter {
+    pub container_ids: Vec<String>,
+    pub limit: u32,
+    /// Applied to both the reply and the entry it replies to
+    pub author_policy: AuthorPolicy,
+}
+
+impl Default for RepliesFilter {
+    fn default() -> Self {
+        Self {
+            container_ids: Vec::new(),
+            limit: 10,
+            author_policy: AuthorPolicy::default(),
+        }
+    }
+}
+
+/// Without a `content`, the counts of all n-grams matching the rest of the
+/// filter are summed up.
 #[derive(Debug)]
 pub struct NgramsByContentFilter {
-    pub content: String,
+    pub content: Option<String>,
+    pub length: Option<u32>,
+    pub sender_id: Option<String>,
+    pub container_ids: Vec<String>,
+    pub granularity: Granularity,
+    pub author_policy: AuthorPolicy,
+}
+
+#[derive(Debug, Default)]
+pub struct NgramTotalCountFilter {
     pub sender_id: Option<String>,
+    pub length: Option<u32>,
+    pub container_ids: Vec<String>,
+    pub author_policy: AuthorPolicy,
+}
+
+#[derive(Debug)]
+pub struct ActiveSendersFilter {
     pub container_ids: Vec<String>,
+    /// Only entries sent at or after this are counted
+    pub since: Option<i64>,
+    pub limit: u32,
+    pub author_policy: AuthorPolicy,
+}
+
+impl Default for ActiveSendersFilter {
+    fn default() -> Self {
+        Self {
+            container_ids: Vec::new(),
+            since: None,
+            limit: 10,
+            author_policy: AuthorPolicy::default(),
+        }
+    }
 }
@@ -0,0 +1,867 @@
+use super::filters::{
+    ActiveSendersFilter, NgramTotalCountFilter, NgramsByContentFilter, NgramsByCountFilter, Order,
+    RepliesFilter,
+};
+use super::utils::filter_ngrams_by_entry_ids;
+use super::Store;
+use crate::processor::backfill::BackfillCursor;
+use crate::processor::container::Container;
+use crate::processor::digest::DigestSchedule;
+use crate::processor::entry::{
+    AuthorKind, Entry, ReplyPairForRepliesCommand, SenderForDigestCommand,
+};
+use crate::processor::ngram::{NgramForByCountCommand, NgramForStore, NgramsForByContentCommand};
+use crate::processor::settings::GuildSettings;
+use async_trait::async_trait;
+use sqlx::Error;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use tokio::sync::Mutex;
+
+#[derive(PartialEq, Eq, Hash, PartialOrd, Ord, Clone)]
+struct NgramKey {
+    content: String,
+    length: u32,
+    time: i64,
+    sender_id: String,
+    container_id: String,
+    author_kind: AuthorKind,
+}
+
+impl From<&NgramForStore> for NgramKey {
+    fn from(ngram: &NgramForStore) -> Self {
+        Self {
+            content: ngram.content.clone(),
+            length: ngram.length,
+            time: ngram.time,
+            sender_id: ngram.sender_id.clone(),
+            container_id: ngram.container_id.clone(),
+            author_kind: ngram.author_kind,
+        }
+    }
+}
+
+struct StoredEntry {
+    entry: Entry,
+    ngrams_cached: bool,
+}
+
+#[derive(Default)]
+struct State {
+    entries: HashMap<String, StoredEntry>,
+    containers: HashSet<(String, String)>,
+    ngrams: BTreeMap<NgramKey, u32>,
+    metadata: HashMap<String, String>,
+    stopwords: BTreeSet<(String, String)>,
+    digest_schedules: BTreeMap<String, DigestSchedule>,
+    guild_settings: HashMap<String, GuildSettings>,
+    excluded_containers: BTreeSet<(String, String)>,
+    opted_out_sender_ids: BTreeSet<String>,
+    backfill_cursors: HashMap<String, BackfillCursor>,
+}
+
+impl State {
+    /// Returns the IDs of the entries which were not marked as cached before
+    fn mark_entries_as_ngrams_cached(&mut self, entry_ids: &[String]) -> Vec<String> {
+        let mut marked_entry_ids = Vec::new();
+        for entry_id in entry_ids {
+            if let Some(stored_entry) = self.entries.get_mut(entry_id) {
+                if !stored_entry.ngrams_cached {
+                    stored_entry.ngrams_cached = true;
+                    marked_entry_ids.push(entry_id.clone());
+                }
+            }
+        }
+
+        marked_entry_ids
+    }
+
+    fn insert_ngrams(&mut self, ngrams: &[&NgramForStore]) {
+        for ngram in ngrams {
+            *self.ngrams.entry(NgramKey::from(*ngram)).or_insert(0) += 1;
+        }
+    }
+
+    fn subtract_ngrams(&mut self, ngrams: &[NgramForStore]) {
+        for ngram in ngrams {
+            if let Some(count) = self.ngrams.get_mut(&NgramKey::from(ngram)) {
+                *count = count.saturating_sub(1);
+            }
+        }
+        self.ngrams.retain(|_, count| *count > 0);
+    }
+
+    fn insert_entries(&mut self, entries: &[Entry], ngrams_cached: bool) -> Vec<String> {
+        let mut inserted_entry_ids = Vec::new();
+        for entry in entries {
+            if self.entries.contains_key(&entry.entry_id) {
+                continue;
+            }
+
+            self.entries.insert(
+                entry.entry_id.clone(),
+                StoredEntry {
+                    entry: entry.clone(),
+                    ngrams_cached,
+                },
+            );
+            inserted_entry_ids.push(entry.entry_id.clone());
+        }
+
+        inserted_entry_ids
+    }
+
+    fn get_entries_in_container(&self, container_id: &str) -> Vec<&Entry> {
+        let mut entries: Vec<&Entry> = self
+            .entries
+            .values()
+            .map(|stored_entry| &stored_entry.entry)
+            .filter(|entry| entry.container_id == container_id)
+            .collect();
+        entries.sort_by_key(|entry| entry.unix_timestamp);
+
+        entries
+    }
+
+    fn get_entries_without_cached_ngrams(
+        &self,
+        limit: u32,
+        start_id: Option<String>,
+    ) -> Vec<Entry> {
+        let start_timestamp = match start_id {
+            Some(start_id) => match self.entries.get(&start_id) {
+                Some(stored_entry) => Some(stored_entry.entry.unix_timestamp),
+                None => return Vec::new(),
+            },
+            None => None,
+        };
+
+        let mut entries: Vec<&Entry> = self
+            .entries
+            .values()
+            .filter(|stored_entry| !stored_entry.ngrams_cached)
+            .map(|stored_entry| &stored_entry.entry)
+            .filter(|entry| start_timestamp.map_or(true, |t| entry.unix_timestamp < t))
+            .collect();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.unix_timestamp));
+
+        entries.into_iter().take(limit as usize).cloned().collect()
+    }
+
+    fn get_ngrams_by_count(&self, filter: &NgramsByCountFilter) -> Vec<NgramForByCountCommand> {
+        let mut counts: BTreeMap<&str, u32> = BTreeMap::new();
+        for (key, count) in &self.ngrams {
+            if !is_in_containers(&filter.container_ids, &key.container_id)
+                || filter
+                    .sender_id
+                    .as_ref()
+                    .map_or(false, |s| *s != key.sender_id)
+                || filter.length.map_or(false, |l| l != key.length)
+                || (!filter.contents.is_empty() && !filter.contents.contains(&key.content))
+                || filter.stopwords.contains(&key.content)
+                || filter.since.map_or(false, |since| key.time < since)
+                || filter.until.map_or(false, |until| key.time >= until)
+                || !filter.author_policy.allows(key.author_kind, &key.sender_id)
+            {
+                continue;
+            }
+
+            *counts.entry(&key.content).or_insert(0) += count;
+        }
+
+        let mut ngrams: Vec<NgramForByCountCommand> = counts
+            .into_iter()
+            .map(|(content, count)| NgramForByCountCommand {
+                content: content.to_string(),
+                count,
+            })
+            .collect();
+        match filter.order {
+            Order::Ascending => ngrams.sort_by_key(|ngram| ngram.count),
+            Order::Descending => ngrams.sort_by_key(|ngram| std::cmp::Reverse(ngram.count)),
+        }
+
+        ngrams
+            .into_iter()
+            .skip(filter.offset as usize)
+            .take(filter.limit as usize)
+            .collect()
+    }
+
+    fn get_ngram_by_content(
+        &self,
+        filter: &NgramsByContentFilter,
+    ) -> Vec<NgramsForByContentCommand> {
+        let mut counts: BTreeMap<i64, u32> = BTreeMap::new();
+        for (key, count) in &self.ngrams {
+            if filter
+                .content
+                .as_ref()
+                .map_or(true, |content| *content == key.content)
+                && filter.length.map_or(true, |length| length == key.length)
+                && is_in_containers(&filter.container_ids, &key.container_id)
+                && filter
+                    .sender_id
+                    .as_ref()
+                    .map_or(true, |s| *s == key.sender_id)
+                && filter.author_policy.allows(key.author_kind, &key.sender_id)
+            {
+                *counts
+                    .entry(filter.granularity.truncate(key.time))
+                    .or_insert(0) += count;
+            }
+        }
+
+        counts
+            .into_iter()
+            .map(|(time, count)| NgramsForByContentCommand { count, time })
+            .collect()
+    }
+
+    fn get_ngram_total_count(&self, filter: &NgramTotalCountFilter) -> u64 {
+        self.ngrams
+            .iter()
+            .filter(|(key, _)| {
+                filter.length.map_or(true, |length| length == key.length)
+                    && is_in_containers(&filter.container_ids, &key.container_id)
+                    && filter
+                        .sender_id
+                        .as_ref()
+                        .map_or(true, |s| *s == key.sender_id)
+                    && filter.author_policy.allows(key.author_kind, &key.sender_id)
+            })
+            .map(|(_, count)| u64::from(*count))
+            .sum()
+    }
+
+    fn get_reply_pairs(&self, filter: &RepliesFilter) -> Vec<ReplyPairForRepliesCommand> {
+        let mut counts: BTreeMap<(&str, &str), u32> = BTreeMap::new();
+        for stored_entry in self.entries.values() {
+            let entry = &stored_entry.entry;
+            if !is_in_containers(&filter.container_ids, &entry.container_id) {
+                continue;
+            }
+
+            let parent_entry = match entry
+                .parent_entry_id
+                .as_ref()
+                .and_then(|parent_entry_id| self.entries.get(parent_entry_id))
+            {
+                Some(parent_entry) => &parent_entry.entry,
+                None => continue,
+            };
+            if parent_entry.sender_id == entry.sender_id
+                || !filter
+                    .author_policy
+                    .allows(entry.author_kind, &entry.sender_id)
+                || !filter
+                    .author_policy
+                    .allows(parent_entry.author_kind, &parent_entry.sender_id)
+            {
+                continue;
+            }
+
+            *counts
+                .entry((&entry.sender_id, &parent_entry.sender_id))
+                .or_insert(0) += 1;
+        }
+
+        let mut reply_pairs: Vec<ReplyPairForRepliesCommand> = counts
+            .into_iter()
+            .map(
+                |((sender_id, parent_sender_id), count)| ReplyPairForRepliesCommand {
+                    sender_id: sender_id.to_string(),
+                    parent_sender_id: parent_sender_id.to_string(),
+                    count,
+                },
+            )
+            .collect();
+        reply_pairs.sort_by_key(|reply_pair| std::cmp::Reverse(reply_pair.count));
+        reply_pairs.truncate(filter.limit as usize);
+
+        reply_pairs
+    }
+
+    fn get_active_senders(&self, filter: &ActiveSendersFilter) -> Vec<SenderForDigestCommand> {
+        let mut counts: BTreeMap<&str, u32> = BTreeMap::new();
+        for stored_entry in self.entries.values() {
+            let entry = &stored_entry.entry;
+            if !is_in_containers(&filter.container_ids, &entry.container_id)
+                || filter
+                    .since
+                    .map_or(false, |since| entry.unix_timestamp < since)
+                || !filter
+                    .author_policy
+                    .allows(entry.author_kind, &entry.sender_id)
+            {
+                continue;
+            }
+
+            *counts.entry(&entry.sender_id).or_insert(0) += 1;
+        }
+
+        let mut senders: Vec<SenderForDigestCommand> = counts
+            .into_iter()
+            .map(|(sender_id, count)| SenderForDigestCommand {
+                sender_id: sender_id.to_string(),
+                count,
+            })
+            .collect();
+        // The sort is stable, so senders with the same count stay alphabetical
+        senders.sort_by_key(|sender| std::cmp::Reverse(sender.count));
+        senders.truncate(filter.limit as usize);
+
+        senders
+    }
+}
+
+fn is_in_containers(container_ids: &[String], container_id: &str) -> bool {
+    container_ids.is_empty() || container_ids.iter().any(|id| id == container_id)
+}
+
+/// A store which keeps everything in memory. Useful for testing the
+/// processor without touching the disk.
+#[derive(Default)]
+pub struct Memory {
+    state: Mutex<State>,
+}
+
+impl Memory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl Store for Memory {
+    async fn add_ngrams(
+        &self,
+        ngrams: &[NgramForStore],
+        entry_ids: &[String],
+    ) -> Result<(), Error> {
+        let mut state = self.state.lock().await;
+        let marked_entry_ids = state.mark_entries_as_ngrams_cached(entry_ids);
+        state.insert_ngrams(&filter_ngrams_by_entry_ids(ngrams, &marked_entry_ids));
+        drop(state);
+
+        Ok(())
+    }
+
+    async fn add_entries(&self, entries: &[Entry], ngrams: &[NgramForStore]) -> Result<(), Error> {
+        let mut state = self.state.lock().await;
+        let inserted_entry_ids = state.insert_entries(entries, true);
+        state.insert_ngrams(&filter_ngrams_by_entry_ids(ngrams, &inserted_entry_ids));
+        drop(state);
+
+        Ok(())
+    }
+
+    async fn delete_entries(
+        &self,
+        entry_ids: &[String],
+        ngrams: &[NgramForStore],
+    ) -> Result<(), Error> {
+        let mut state = self.state.lock().await;
+        state.subtract_ngrams(ngrams);
+        for entry_id in entry_ids {
+            state.entries.remove(entry_id);
+        }
+        drop(state);
+
+        Ok(())
+    }
+
+    async fn replace_entries(
+        &self,
+        entries: &[Entry],
+        old_ngrams: &[NgramForStore],
+        ngrams: &[NgramForStore],
+    ) -> Result<(), Error> {
+        let mut state = self.state.lock().await;
+        state.subtract_ngrams(old_ngrams);
+        for entry in entries {
+            state.entries.remove(&entry.entry_id);
+        }
+        let inserted_entry_ids = state.insert_entries(entries, true);
+        state.insert_ngrams(&filter_ngrams_by_entry_ids(ngrams, &inserted_entry_ids));
+        drop(state);
+
+        Ok(())
+    }
+    async fn reset_ngrams(&self) -> Result<(), Error> {
+        let mut state = self.state.lock().await;
+        state.ngrams.clear();
+        for stored_entry in state.entries.values_mut() {
+            stored_entry.ngrams_cached = false;
+        }
+        drop(state);
+
+        Ok(())
+    }
+
+    async fn get_metadata(&self, key: &str) -> Result<Option<String>, Error> {
+        Ok(self.state.lock().await.metadata.get(key).cloned())
+    }
+
+    async fn set_metadata(&self, key: &str, value: &str) -> Result<(), Error> {
+        self.state
+            .lock()
+            .await
+            .metadata
+            .insert(key.to_string(), value.to_string());
+
+        Ok(())
+    }
+
+    async fn get_custom_stopwords(&self, container_id: &str) -> Result<Vec<String>, Error> {
+        Ok(self
+            .state
+            .lock()
+            .await
+            .stopwords
+            .iter()
+            .filter(|(stopword_container_id, _)| stopword_container_id == container_id)
+            .map(|(_, word)| word.clone())
+            .collect())
+    }
+
+    async fn add_custom_stopwords(
+        &self,
+        container_id: &str,
+        words: &[String],
+    ) -> Result<(), Error> {
+        let mut state = self.state.lock().await;
+        for word in words {
+            state
+                .stopwords
+                .insert((container_id.to_string(), word.clone()));
+        }
+        drop(state);
+
+        Ok(())
+    }
+
+    async fn remove_custom_stopwords(
+        &self,
+        container_id: &str,
+        words: &[String],
+    ) -> Result<(), Error> {
+        let mut state = self.state.lock().await;
+        for word in words {
+            state
+                .stopwords
+                .remove(&(container_id.to_string(), word.clone()));
+        }
+        drop(state);
+
+        Ok(())
+    }
+
+    async fn get_digest_schedules(&self) -> Result<Vec<DigestSchedule>, Error> {
+        Ok(self
+            .state
+            .lock()
+            .await
+            .digest_schedules
+            .values()
+            .cloned()
+            .collect())
+    }
+
+    async fn set_digest_schedule(&self, schedule: &DigestSchedule) -> Result<(), Error> {
+        self.state
+            .lock()
+            .await
+            .digest_schedules
+            .insert(schedule.container_id.clone(), schedule.clone());
+
+        Ok(())
+    }
+
+    async fn remove_digest_schedule(&self, container_id: &str) -> Result<(), Error> {
+        self.state
+            .lock()
+            .await
+            .digest_schedules
+            .remove(container_id);
+
+        Ok(())
+    }
+
+    async fn set_digest_last_run_time(
+        &self,
+        container_id: &str,
+        last_run_time: i64,
+    ) -> Result<(), Error> {
+        if let Some(schedule) = self
+            .state
+            .lock()
+            .await
+            .digest_schedules
+            .get_mut(container_id)
+        {
+            schedule.last_run_time = last_run_time;
+        }
+
+        Ok(())
+    }
+
+    async fn get_guild_settings(&self, container_id: &str) -> Result<Option<GuildSettings>, Error> {
+        Ok(self
+            .state
+            .lock()
+            .await
+            .guild_settings
+            .get(container_id)
+            .cloned())
+    }
+
+    async fn set_guild_settings(&self, settings: &GuildSettings) -> Result<(), Error> {
+        self.state
+            .lock()
+            .await
+            .guild_settings
+            .insert(settings.container_id.clone(), settings.clone());
+
+        Ok(())
+    }
+
+    async fn get_excluded_container_ids(&self, container_id: &str) -> Result<Vec<String>, Error> {
+        Ok(self
+            .state
+            .lock()
+            .await
+            .excluded_containers
+            .iter()
+            .filter(|(excluding_container_id, _)| excluding_container_id == container_id)
+            .map(|(_, excluded_container_id)| excluded_container_id.clone())
+            .collect())
+    }
+
+    async fn add_excluded_container_id(
+        &self,
+        container_id: &str,
+        excluded_container_id: &str,
+    ) -> Result<(), Error> {
+        self.state
+            .lock()
+            .await
+            .excluded_containers
+            .insert((container_id.to_string(), excluded_container_id.to_string()));
+
+        Ok(())
+    }
+
+    async fn remove_excluded_container_id(
+        &self,
+        container_id: &str,
+        excluded_container_id: &str,
+    ) -> Result<(), Error> {
+        self.state
+            .lock()
+            .await
+            .excluded_containers
+            .remove(&(container_id.to_string(), excluded_container_id.to_string()));
+
+        Ok(())
+    }
+
+    async fn get_opted_out_sender_ids(&self) -> Result<Vec<String>, Error> {
+        Ok(self
+            .state
+            .lock()
+            .await
+            .opted_out_sender_ids
+            .iter()
+            .cloned()
+            .collect())
+    }
+
+    async fn add_opted_out_sender_id(&self, sender_id: &str) -> Result<(), Error> {
+        self.state
+            .lock()
+            .await
+            .opted_out_sender_ids
+            .insert(sender_id.to_string());
+
+        Ok(())
+    }
+
+    async fn remove_opted_out_sender_id(&self, sender_id: &str) -> Result<(), Error> {
+        self.state
+            .lock()
+            .await
+            .opted_out_sender_ids
+            .remove(sender_id);
+
+        Ok(())
+    }
+
+    async fn get_backfill_cursor(
+        &self,
+        container_id: &str,
+    ) -> Result<Option<BackfillCursor>, Error> {
+        Ok(self
+            .state
+            .lock()
+            .await
+            .backfill_cursors
+            .get(container_id)
+            .cloned())
+    }
+
+    async fn set_backfill_cursor(&self, cursor: &BackfillCursor) -> Result<(), Error> {
+        self.state
+            .lock()
+            .await
+            .backfill_cursors
+            .insert(cursor.container_id.clone(), cursor.clone());
+
+        Ok(())
+    }
+
+    async fn remove_backfill_cursor(&self, container_id: &str) -> Result<(), Error> {
+        self.state
+            .lock()
+            .await
+            .backfill_cursors
+            .remove(container_id);
+
+        Ok(())
+    }
+
+    async fn get_entry_ids_by_sender(&self, sender_id: &str) -> Result<Vec<String>, Error> {
+        Ok(self
+            .state
+            .lock()
+            .await
+            .entries
+            .values()
+            .filter(|stored_entry| stored_entry.entry.sender_id == sender_id)
+            .map(|stored_entry| stored_entry.entry.entry_id.clone())
+            .collect())
+    }
+
+    async fn get_entry_ids_in_container(&self, container_id: &str) -> Result<Vec<String>, Error> {
+        Ok(self
+            .state
+            .lock()
+            .await
+            .get_entries_in_container(container_id)
+            .iter()
+            .map(|entry| entry.entry_id.clone())
+            .collect())
+    }
+
+    async fn get_entries_with_cached_ngrams(
+        &self,
+        entry_ids: &[String],
+    ) -> Result<Vec<Entry>, Error> {
+        let state = self.state.lock().await;
+
+        Ok(entry_ids
+            .iter()
+            .filter_map(|entry_id| state.entries.get(entry_id))
+            .filter(|stored_entry| stored_entry.ngrams_cached)
+            .map(|stored_entry| stored_entry.entry.clone())
+            .collect())
+    }
+
+    async fn add_container(&self, container: &Container) -> Result<(), Error> {
+        let mut state = self.state.lock().await;
+        if !state
+            .containers
+            .iter()
+            .any(|(id, _)| id == &container.container_id)
+        {
+            state.containers.insert((
+                container.container_id.clone(),
+                container.container_parent_id.clone(),
+            ));
+        }
+        drop(state);
+
+        Ok(())
+    }
+
+    async fn set_container_parent(&self, container: &Container) -> Result<(), Error> {
+        let mut state = self.state.lock().await;
+        state
+            .containers
+            .retain(|(id, _)| id != &container.container_id);
+        state.containers.insert((
+            container.container_id.clone(),
+            container.container_parent_id.clone(),
+        ));
+        drop(state);
+
+        Ok(())
+    }
+
+    async fn remove_container(&self, container_id: &str) -> Result<(), Error> {
+        self.state
+            .lock()
+            .await
+            .containers
+            .retain(|(id, _)| id != container_id);
+
+        Ok(())
+    }
+
+    async fn get_last_entry_id_in_container(&self, container_id: &str) -> Result<String, Error> {
+        self.state
+            .lock()
+            .await
+            .get_entries_in_container(container_id)
+            .last()
+            .map(|entry| entry.entry_id.clone())
+            .ok_or(Error::RowNotFound)
+    }
+
+    async fn get_first_entry_id_in_container(&self, container_id: &str) -> Result<String, Error> {
+        self.state
+            .lock()
+            .await
+            .get_entries_in_container(container_id)
+            .first()
+            .map(|entry| entry.entry_id.clone())
+            .ok_or(Error::RowNotFound)
+    }
+
+    async fn get_entries_without_cached_ngrams(
+        &self,
+        limit: u32,
+        start_id: Option<String>,
+    ) -> Result<Vec<Entry>, Error> {
+        Ok(self
+            .state
+            .lock()
+            .await
+            .get_entries_without_cached_ngrams(limit, start_id))
+    }
+
+    async fn get_child_container_ids(&self, container_id: &str) -> Result<Vec<String>, Error> {
+        Ok(self
+            .state
+            .lock()
+            .await
+            .containers
+            .iter()
+            .filter(|(_, parent_id)| parent_id == container_id)
+            .map(|(id, _)| id.clone())
+            .collect())
+    }
+
+    async fn get_ngram_count_in_container(&self, container_id: &str) -> Result<i32, Error> {
+        let count = self
+            .state
+            .lock()
+            .await
+            .ngrams
+            .keys()
+            .filter(|key| key.container_id == container_id)
+            .count();
+
+        Ok(i32::try_from(count).unwrap_or(i32::MAX))
+    }
+
+    async fn get_entries_count_in_container(&self, container_id: &str) -> Result<i32, Error> {
+        let count = self
+            .state
+            .lock()
+            .await
+            .get_entries_in_container(container_id)
+            .len();
+
+        Ok(i32::try_from(count).unwrap_or(i32::MAX))
+    }
+
+    async fn get_ngrams_by_count(
+        &self,
+        filter: &NgramsByCountFilter,
+    ) -> Result<Vec<NgramForByCountCommand>, Error> {
+        Ok(self.state.lock().await.get_ngrams_by_count(filter))
+    }
+
+    async fn get_ngram_by_content(
+        &self,
+        filter: &NgramsByContentFilter,
+    ) -> Result<Vec<NgramsForByContentCommand>, Error> {
+        Ok(self.state.lock().await.get_ngram_by_content(filter))
+    }
+
+    async fn get_ngram_total_count(&self, filter: &NgramTotalCountFilter) -> Result<u64, Error> {
+        Ok(self.state.lock().await.get_ngram_total_count(filter))
+    }
+
+    async fn get_reply_pairs(
+        &self,
+        filter: &RepliesFilter,
+    ) -> Result<Vec<ReplyPairForRepliesCommand>, Error> {
+        Ok(self.state.lock().await.get_reply_pairs(filter))
+    }
+
+    async fn get_active_senders(
+        &self,
+        filter: &ActiveSendersFilter,
+    ) -> Result<Vec<SenderForDigestCommand>, Error> {
+        Ok(self.state.lock().await.get_active_senders(filter))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Memory;
+    use crate::processor::tokenizer::TokenizerKind;
+    use crate::processor::Processor;
+    use crate::store::scenarios;
+
+    const PREFIX: &str = "memory";
+
+    fn make_processor() -> Processor<Memory> {
+        Processor::new(
+            Box::new(Memory::new()),
+            TokenizerKind::default().into_tokenizer(),
+        )
+        .with_author_policy(scenarios::make_author_policy())
+    }
+
+    #[tokio::test]
+    async fn add_entries() {
+        scenarios::add_entries(&make_processor(), PREFIX).await;
+    }
+
+    #[tokio::test]
+    async fn cache_ngrams() {
+        scenarios::cache_ngrams(&make_processor(), PREFIX).await;
+    }
+
+    #[tokio::test]
+    async fn get_ngrams_by_count() {
+        scenarios::get_ngrams_by_count(&make_processor(), PREFIX).await;
+    }
+
+    #[tokio::test]
+    async fn delete_entries() {
+        scenarios::delete_entries(&make_processor(), PREFIX).await;
+    }
+
+    #[tokio::test]
+    async fn update_entry() {
+        scenarios::update_entry(&make_processor(), PREFIX).await;
+    }
+
+    #[tokio::test]
+    async fn opt_out_sender() {
+        scenarios::opt_out_sender(&make_processor(), PREFIX).await;
+    }
+
+    #[tokio::test]
+    async fn exclude_containers() {
+        scenarios::exclude_containers(&make_processor(), PREFIX).await;
+    }
+
+    #[tokio::test]
+    async fn reclassify_authors() {
+        scenarios::reclassify_authors(&make_processor(), PREFIX).await;
+    }
+}
@@ -0,0 +1,422 @@
+//! Scenarios driving the processor, shared by the tests of each store. The
+//! IDs of everything a scenario stores start with `prefix`, so that scenarios
+//! can share a database.
+
+use super::Store;
+use crate::processor::container::Container;
+use crate::processor::entry::{AuthorKind, AuthorPolicy, Entry};
+use crate::processor::Processor;
+
+const UNIX_TIMESTAMP: i64 = 1_700_000_000;
+/// `get_ngrams_in_word_list` leaves out the first word of an entry, so the
+/// contents are prefixed with a word which is not asserted on
+const CONTENT_PREFIX: &str = "so";
+
+/// Bots are ignored, like by default in the bot. The processors the
+/// scenarios run on are constructed with this policy.
+pub fn make_author_policy() -> AuthorPolicy {
+    AuthorPolicy {
+        ignored_author_kinds: vec![AuthorKind::Bot],
+        allowed_sender_ids: Vec::new(),
+    }
+}
+
+fn get_guild_id(prefix: &str) -> String {
+    format!("{}-guild", prefix)
+}
+
+fn make_entry(prefix: &str, entry_id: &str, sender_id: &str, content: &str) -> Entry {
+    Entry {
+        entry_id: format!("{}-{}", prefix, entry_id),
+        container_id: format!("{}-channel", prefix),
+        sender_id: format!("{}-{}", prefix, sender_id),
+        unix_timestamp: UNIX_TIMESTAMP,
+        content: format!("{} {}", CONTENT_PREFIX, content),
+        parent_entry_id: None,
+        author_kind: AuthorKind::User,
+    }
+}
+
+async fn add_entries_in_channel<S: Store + ?Sized>(
+    processor: &Processor<S>,
+    prefix: &str,
+    entries: &[Entry],
+) {
+    for container in [
+        Container {
+            container_id: get_guild_id(prefix),
+            container_parent_id: String::from("discord"),
+        },
+        Container {
+            container_id: format!("{}-channel", prefix),
+            container_parent_id: get_guild_id(prefix),
+        },
+    ] {
+        processor
+            .add_container(&container)
+            .await
+            .expect("Failed to add container");
+    }
+
+    processor
+        .add_entries(entries)
+        .await
+        .expect("Failed to add entries");
+}
+
+/// Returns the unigrams in the guild as `content=count`, sorted by content.
+async fn get_unigram_counts<S: Store + ?Sized>(
+    processor: &Processor<S>,
+    prefix: &str,
+) -> Vec<String> {
+    let mut unigram_counts: Vec<String> = processor
+        .get_ngrams_by_count(None, Some(1), Some(100), &[get_guild_id(prefix)], None, &[])
+        .await
+        .expect("Failed to get n-grams")
+        .into_iter()
+        .map(|ngram| format!("{}={}", ngram.content, ngram.count))
+        .collect();
+    unigram_counts.sort();
+
+    unigram_counts
+}
+
+async fn get_entries_count<S: Store + ?Sized>(processor: &Processor<S>, prefix: &str) -> i32 {
+    processor
+        .get_entries_count_in_container(&get_guild_id(prefix))
+        .await
+        .expect("Failed to count entries")
+}
+
+pub async fn add_entries<S: Store + ?Sized>(processor: &Processor<S>, prefix: &str) {
+    let entries = [
+        make_entry(prefix, "1", "alice", "Hello world"),
+        make_entry(prefix, "2", "bob", "hello there"),
+    ];
+    add_entries_in_channel(processor, prefix, &entries).await;
+    // Entries which are already stored are not counted again
+    add_entries_in_channel(processor, prefix, &entries[..1]).await;
+
+    assert_eq!(
+        get_unigram_counts(processor, prefix).await,
+        ["hello=2", "there=1", "world=1"]
+    );
+    assert_eq!(get_entries_count(processor, prefix).await, 2);
+    // The channel counts its own entries
+    assert_eq!(
+        processor
+            .get_entries_count_in_container(&format!("{}-channel", prefix))
+            .await
+            .expect("Failed to count entries"),
+        2
+    );
+}
+
+pub async fn cache_ngrams<S: Store + ?Sized>(processor: &Processor<S>, prefix: &str) {
+    add_entries_in_channel(
+        processor,
+        prefix,
+        &[
+            make_entry(prefix, "1", "alice", "Hello world"),
+            make_entry(prefix, "2", "bob", "hello there"),
+        ],
+    )
+    .await;
+
+    processor
+        .reset_ngrams_if_tokenizer_changed()
+        .await
+        .expect("Failed to reset n-grams");
+    processor
+        .cache_ngrams()
+        .await
+        .expect("Failed to cache n-grams");
+    assert_eq!(
+        get_unigram_counts(processor, prefix).await,
+        ["hello=2", "there=1", "world=1"]
+    );
+
+    processor
+        .rebuild_ngrams()
+        .await
+        .expect("Failed to rebuild n-grams");
+    assert_eq!(
+        get_unigram_counts(processor, prefix).await,
+        ["hello=2", "there=1", "world=1"]
+    );
+}
+
+pub async fn get_ngrams_by_count<S: Store + ?Sized>(processor: &Processor<S>, prefix: &str) {
+    add_entries_in_channel(
+        processor,
+        prefix,
+        &[
+            make_entry(prefix, "1", "alice", "lol lol lol lol"),
+            make_entry(prefix, "2", "bob", "lol what"),
+            make_entry(prefix, "3", "bob", "what lol"),
+        ],
+    )
+    .await;
+
+    let ngrams: Vec<String> = processor
+        .get_ngrams_by_count(None, None, Some(2), &[get_guild_id(prefix)], None, &[])
+        .await
+        .expect("Failed to get n-grams")
+        .into_iter()
+        .map(|ngram| format!("{}={}", ngram.content, ngram.count))
+        .collect();
+    assert_eq!(ngrams, ["lol=6", "lol lol=3"]);
+
+    // The stored n-grams are summed per sender, so there are fewer of them
+    // than occurrences
+    let guild_id = get_guild_id(prefix);
+    assert_eq!(
+        processor
+            .get_ngram_occurrences_in_container(&guild_id)
+            .await
+            .expect("Failed to count n-gram occurrences"),
+        16
+    );
+    assert_eq!(
+        processor
+            .get_ngram_count_in_container(&guild_id)
+            .await
+            .expect("Failed to count n-grams"),
+        8
+    );
+
+    let bob_ngrams: Vec<String> = processor
+        .get_ngrams_by_count(
+            Some(format!("{}-bob", prefix)),
+            Some(1),
+            None,
+            &[get_guild_id(prefix)],
+            None,
+            &[String::from("what")],
+        )
+        .await
+        .expect("Failed to get n-grams")
+        .into_iter()
+        .map(|ngram| format!("{}={}", ngram.content, ngram.count))
+        .collect();
+    assert_eq!(bob_ngrams, ["lol=2"]);
+}
+
+pub async fn delete_entries<S: Store + ?Sized>(processor: &Processor<S>, prefix: &str) {
+    add_entries_in_channel(
+        processor,
+        prefix,
+        &[
+            make_entry(prefix, "1", "alice", "Hello world"),
+            make_entry(prefix, "2", "bob", "hello there"),
+        ],
+    )
+    .await;
+
+    processor
+        .delete_entries(&[format!("{}-1", prefix), format!("{}-missing", prefix)])
+        .await
+        .expect("Failed to delete entries");
+
+    assert_eq!(
+        get_unigram_counts(processor, prefix).await,
+        ["hello=1", "there=1"]
+    );
+    assert_eq!(get_entries_count(processor, prefix).await, 1);
+}
+
+pub async fn update_entry<S: Store + ?Sized>(processor: &Processor<S>, prefix: &str) {
+    add_entries_in_channel(
+        processor,
+        prefix,
+        &[
+            make_entry(prefix, "1", "alice", "Hello world"),
+            make_entry(prefix, "2", "bob", "hello there"),
+        ],
+    )
+    .await;
+
+    processor
+        .update_entry(make_entry(prefix, "2", "bob", "hello you"))
+        .await
+        .expect("Failed to update entry");
+    assert_eq!(
+        get_unigram_counts(processor, prefix).await,
+        ["hello=2", "world=1", "you=1"]
+    );
+    assert_eq!(get_entries_count(processor, prefix).await, 2);
+
+    // Entries which were not stored before are added
+    processor
+        .update_entry(make_entry(prefix, "3", "bob", "you"))
+        .await
+        .expect("Failed to update entry");
+    assert_eq!(
+        get_unigram_counts(processor, prefix).await,
+        ["hello=2", "world=1", "you=2"]
+    );
+    assert_eq!(get_entries_count(processor, prefix).await, 3);
+}
+
+pub async fn opt_out_sender<S: Store + ?Sized>(processor: &Processor<S>, prefix: &str) {
+    let alice_id = format!("{}-alice", prefix);
+    add_entries_in_channel(
+        processor,
+        prefix,
+        &[
+            make_entry(prefix, "1", "alice", "hello world"),
+            make_entry(prefix, "2", "bob", "hello there"),
+        ],
+    )
+    .await;
+
+    processor
+        .opt_out_sender(&alice_id)
+        .await
+        .expect("Failed to opt out");
+    assert!(processor
+        .is_sender_opted_out(&alice_id)
+        .await
+        .expect("Failed to check the opt-out"));
+    add_entries_in_channel(
+        processor,
+        prefix,
+        &[make_entry(prefix, "3", "alice", "hello again")],
+    )
+    .await;
+    assert_eq!(
+        get_unigram_counts(processor, prefix).await,
+        ["hello=2", "there=1", "world=1"]
+    );
+
+    let forgotten_count = processor
+        .forget_sender(&alice_id)
+        .await
+        .expect("Failed to forget sender");
+    assert_eq!(forgotten_count, 1);
+    assert_eq!(
+        get_unigram_counts(processor, prefix).await,
+        ["hello=1", "there=1"]
+    );
+    assert_eq!(get_entries_count(processor, prefix).await, 1);
+
+    processor
+        .opt_in_sender(&alice_id)
+        .await
+        .expect("Failed to opt in");
+    add_entries_in_channel(
+        processor,
+        prefix,
+        &[make_entry(prefix, "3", "alice", "hello again")],
+    )
+    .await;
+    assert_eq!(
+        get_unigram_counts(processor, prefix).await,
+        ["again=1", "hello=2", "there=1"]
+    );
+}
+
+pub async fn exclude_containers<S: Store + ?Sized>(processor: &Processor<S>, prefix: &str) {
+    let guild_id = get_guild_id(prefix);
+    let category_id = format!("{}-category", prefix);
+    let channel_id = format!("{}-channel", prefix);
+    let thread_id = format!("{}-thread", prefix);
+    let other_channel_id = format!("{}-other-channel", prefix);
+    for (container_id, container_parent_id) in [
+        (&guild_id, "discord"),
+        (&category_id, &guild_id),
+        (&channel_id, &category_id),
+        (&thread_id, &channel_id),
+        (&other_channel_id, &guild_id),
+    ] {
+        processor
+            .set_container_parent(&Container {
+                container_id: container_id.clone(),
+                container_parent_id: container_parent_id.to_string(),
+            })
+            .await
+            .expect("Failed to add container");
+    }
+
+    let mut entries = [
+        make_entry(prefix, "1", "alice", "channel"),
+        make_entry(prefix, "2", "alice", "thread"),
+        make_entry(prefix, "3", "alice", "other"),
+    ];
+    entries[1].container_id.clone_from(&thread_id);
+    entries[2].container_id.clone_from(&other_channel_id);
+    processor
+        .add_entries(&entries)
+        .await
+        .expect("Failed to add entries");
+    assert_eq!(get_entries_count(processor, prefix).await, 3);
+
+    // Exclusions of the guild apply at any depth below it
+    for (excluded_container_id, unigram_counts) in [
+        (&category_id, vec!["other=1"]),
+        (&thread_id, vec!["channel=1", "other=1"]),
+    ] {
+        processor
+            .add_excluded_container_id(&guild_id, excluded_container_id)
+            .await
+            .expect("Failed to exclude container");
+        assert_eq!(get_unigram_counts(processor, prefix).await, unigram_counts);
+        processor
+            .remove_excluded_container_id(&guild_id, excluded_container_id)
+            .await
+            .expect("Failed to include container");
+    }
+
+    // A channel moved out of the category is no longer excluded with it
+    processor
+        .set_container_parent(&Container {
+            container_id: channel_id.clone(),
+            container_parent_id: guild_id.clone(),
+        })
+        .await
+        .expect("Failed to move container");
+    processor
+        .add_container(&Container {
+            container_id: channel_id,
+            container_parent_id: category_id.clone(),
+        })
+        .await
+        .expect("Failed to add container");
+    processor
+        .add_excluded_container_id(&guild_id, &category_id)
+        .await
+        .expect("Failed to exclude container");
+    assert_eq!(
+        get_unigram_counts(processor, prefix).await,
+        ["channel=1", "other=1", "thread=1"]
+    );
+    assert_eq!(get_entries_count(processor, prefix).await, 3);
+}
+
+pub async fn reclassify_authors<S: Store + ?Sized>(processor: &Processor<S>, prefix: &str) {
+    // Entries stored before their author kind was known were stored as sent
+    // by users
+    add_entries_in_channel(
+        processor,
+        prefix,
+        &[
+            make_entry(prefix, "1", "alice", "hello world"),
+            make_entry(prefix, "2", "bob", "hello there"),
+        ],
+    )
+    .await;
+
+    let mut webhook_entry = make_entry(prefix, "1", "alice", "hello world");
+    webhook_entry.author_kind = AuthorKind::Webhook;
+    let mut bot_entry = make_entry(prefix, "2", "bob", "hello there");
+    bot_entry.author_kind = AuthorKind::Bot;
+    add_entries_in_channel(processor, prefix, &[webhook_entry, bot_entry]).await;
+
+    // The webhook entry is still allowed and counted once, while the bot
+    // entry no longer is
+    assert_eq!(
+        get_unigram_counts(processor, prefix).await,
+        ["hello=1", "world=1"]
+    );
+    assert_eq!(get_entries_count(processor, prefix).await, 1);
+}
@@ -0,0 +1,1089 @@
+use super::filters::{
+    self, ActiveSendersFilter, NgramTotalCountFilter, NgramsByContentFilter, NgramsByCountFilter,
+    RepliesFilter,
+};
+use super::utils::{
+    build_author_policy_clause, build_in_clause, filter_ngrams_by_entry_ids, parse_author_kind,
+};
+use super::Store;
+use crate::processor::backfill::BackfillCursor;
+use crate::processor::container;
+use crate::processor::digest::DigestSchedule;
+use crate::processor::entry::{Entry, ReplyPairForRepliesCommand, SenderForDigestCommand};
+use crate::processor::ngram::NgramsForByContentCommand;
+use crate::processor::ngram::{Granularity, NgramForByCountCommand, NgramForStore};
+use crate::processor::settings::GuildSettings;
+use async_trait::async_trait;
+use sqlx::migrate;
+use sqlx::migrate::MigrateError;
+use sqlx::sqlite::{
+    SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions, SqliteSynchronous,
+};
+use sqlx::Error;
+use sqlx::QueryBuilder;
+use sqlx::Row;
+use sqlx::{migrate::MigrateDatabase, Sqlite, SqliteConnection};
+use std::collections::HashMap;
+
+/// Limit as per <https://stackoverflow.com/a/15860818/10822785>
+const CHUNK_SIZE: usize = 500;
+const MAX_CONNECTIONS: u32 = 10;
+
+pub struct Sql {
+    pool: SqlitePool,
+}
+
+impl Sql {
+    pub async fn new(file_path: &str) -> Result<Self, Error> {
+        if !Sqlite::database_exists(file_path).await.unwrap_or(false) {
+            log::info!("Database does not exist - creating...");
+            Sqlite::create_database(file_path).await?;
+            log::info!("Database created successfully!");
+        }
+
+        // WAL allows reads to proceed while we are writing
+        let connect_options = SqliteConnectOptions::new()
+            .filename(file_path)
+            .journal_mode(SqliteJournalMode::Wal)
+            .synchronous(SqliteSynchronous::Normal);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(MAX_CONNECTIONS)
+            .connect_with(connect_options)
+            .await?;
+
+        let sql = Self { pool };
+
+        sql.migrate().await?;
+
+        Ok(sql)
+    }
+
+    async fn migrate(&self) -> Result<(), MigrateError> {
+        migrate!("./src/migrations").run(&self.pool).await
+    }
+}
+
+/// Returns an SQL expression truncating the `time` column of the `ngrams`
+/// table to the start of its bucket
+const fn get_bucket_time_expression(granularity: Granularity) -> &'static str {
+    match granularity {
+        Granularity::Hour => "time",
+        Granularity::Day => "time - time % 86400",
+        Granularity::Week => "time - time % 604800",
+        Granularity::Month => {
+            "CAST(strftime('%s', time, 'unixepoch', 'start of month') AS INTEGER)"
+        }
+    }
+}
+
+/// Returns the IDs of the entries which were not marked as cached before
+async fn mark_entries_as_ngrams_cached(
+    connection: &mut SqliteConnection,
+    entry_ids: &[String],
+) -> Result<Vec<String>, Error> {
+    let mut marked_entry_ids = Vec::new();
+    for entry_ids_chunk in entry_ids.chunks(CHUNK_SIZE) {
+        let mut query_builder = QueryBuilder::new(
+            "UPDATE entries SET ngrams_cached=true WHERE ngrams_cached=false AND",
+        );
+        build_in_clause(&mut query_builder, "entry_id", entry_ids_chunk);
+        query_builder.push(" RETURNING entry_id;");
+
+        let rows = query_builder.build().fetch_all(&mut *connection).await?;
+        marked_entry_ids.extend(rows.iter().map(|row| row.get("entry_id")));
+    }
+
+    Ok(marked_entry_ids)
+}
+
+async fn insert_ngrams(
+    connection: &mut SqliteConnection,
+    ngrams: &[&NgramForStore],
+) -> Result<(), Error> {
+    for ngrams_chunk in ngrams.chunks(CHUNK_SIZE) {
+        let mut query_builder = QueryBuilder::new(
+            "INSERT INTO ngrams (count, content, length, time, sender_id, container_id, author_kind) ",
+        );
+
+        query_builder.push_values(ngrams_chunk, |mut query_builder, ngram| {
+            query_builder
+                .push_bind(1)
+                .push_bind(ngram.content.clone())
+                .push_bind(ngram.length)
+                .push_bind(ngram.time)
+                .push_bind(ngram.sender_id.clone())
+                .push_bind(ngram.container_id.clone())
+                .push_bind(ngram.author_kind.as_str());
+        });
+        query_builder.push(
+            " ON CONFLICT (content, length, time, sender_id, container_id, author_kind) DO UPDATE SET count = count + 1;",
+        );
+        query_builder.build().execute(&mut *connection).await?;
+    }
+
+    Ok(())
+}
+
+async fn subtract_ngrams(
+    connection: &mut SqliteConnection,
+    ngrams: &[NgramForStore],
+) -> Result<(), Error> {
+    let mut ngram_counts: HashMap<(&str, u32, i64, &str, &str, &str), u32> = HashMap::new();
+    for ngram in ngrams {
+        *ngram_counts
+            .entry((
+                &ngram.content,
+                ngram.length,
+                ngram.time,
+                &ngram.sender_id,
+                &ngram.container_id,
+                ngram.author_kind.as_str(),
+            ))
+            .or_insert(0) += 1;
+    }
+
+    for ((content, length, time, sender_id, container_id, author_kind), count) in ngram_counts {
+        sqlx::query(
+            "UPDATE ngrams SET count = count - ? WHERE content=? AND length=? AND time=? AND sender_id=? AND container_id=? AND author_kind=?;",
+        )
+        .bind(count)
+        .bind(content)
+        .bind(length)
+        .bind(time)
+        .bind(sender_id)
+        .bind(container_id)
+        .bind(author_kind)
+        .execute(&mut *connection)
+        .await?;
+    }
+    sqlx::query("DELETE FROM ngrams WHERE count <= 0;")
+        .execute(&mut *connection)
+        .await?;
+
+    Ok(())
+}
+
+async fn insert_entries(
+    connection: &mut SqliteConnection,
+    entries: &[Entry],
+    ngrams_cached: bool,
+) -> Result<Vec<String>, Error> {
+    let mut inserted_entry_ids = Vec::new();
+    for entries_chunk in entries.chunks(CHUNK_SIZE) {
+        let mut query_builder = QueryBuilder::new(
+            "INSERT INTO entries (entry_id, content, sender_id, container_id, unix_timestamp, ngrams_cached, parent_entry_id, author_kind) ",
+        );
+        query_builder.push_values(entries_chunk, |mut query_builder, entry| {
+            query_builder
+                .push_bind(entry.entry_id.clone())
+                .push_bind(entry.content.clone())
+                .push_bind(entry.sender_id.clone())
+                .push_bind(entry.container_id.clone())
+                .push_bind(entry.unix_timestamp)
+                .push_bind(ngrams_cached)
+                .push_bind(entry.parent_entry_id.clone())
+                .push_bind(entry.author_kind.as_str());
+        });
+        query_builder.push(" ON CONFLICT (entry_id) DO NOTHING RETURNING entry_id;");
+
+        let rows = query_builder.build().fetch_all(&mut *connection).await?;
+        inserted_entry_ids.extend(rows.iter().map(|row| row.get("entry_id")));
+    }
+
+    Ok(inserted_entry_ids)
+}
+
+async fn remove_entries(
+    connection: &mut SqliteConnection,
+    entry_ids: &[String],
+) -> Result<(), Error> {
+    for entry_ids_chunk in entry_ids.chunks(CHUNK_SIZE) {
+        let mut query_builder = QueryBuilder::new("DELETE FROM entries WHERE");
+        build_in_clause(&mut query_builder, "entry_id", entry_ids_chunk);
+        query_builder.push(";");
+        query_builder.build().execute(&mut *connection).await?;
+    }
+
+    Ok(())
+}
+
+#[async_trait]
+impl Store for Sql {
+    async fn reset_ngrams(&self) -> Result<(), Error> {
+        let mut transaction = self.pool.begin().await?;
+        sqlx::query!("DELETE FROM ngrams;")
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query!("UPDATE entries SET ngrams_cached=false;")
+            .execute(&mut *transaction)
+            .await?;
+        transaction.commit().await
+    }
+
+    async fn get_metadata(&self, key: &str) -> Result<Option<String>, Error> {
+        let result = sqlx::query!("SELECT value FROM metadata WHERE key=?;", key)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(result.map(|row| row.value))
+    }
+
+    async fn set_metadata(&self, key: &str, value: &str) -> Result<(), Error> {
+        sqlx::query!(
+            "INSERT INTO metadata (key, value) VALUES (?, ?) ON CONFLICT (key) DO UPDATE SET value=excluded.value;",
+            key,
+            value
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_custom_stopwords(&self, container_id: &str) -> Result<Vec<String>, Error> {
+        let rows = sqlx::query!(
+            "SELECT word FROM stopwords WHERE container_id=? ORDER BY word ASC;",
+            container_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| row.word).collect())
+    }
+
+    async fn add_custom_stopwords(
+        &self,
+        container_id: &str,
+        words: &[String],
+    ) -> Result<(), Error> {
+        let mut transaction = self.pool.begin().await?;
+        for word in words {
+            sqlx::query!(
+                "INSERT INTO stopwords (container_id, word) VALUES (?, ?) ON CONFLICT (container_id, word) DO NOTHING;",
+                container_id,
+                word
+            )
+            .execute(&mut *transaction)
+            .await?;
+        }
+        transaction.commit().await
+    }
+
+    async fn remove_custom_stopwords(
+        &self,
+        container_id: &str,
+        words: &[String],
+    ) -> Result<(), Error> {
+        let mut transaction = self.pool.begin().await?;
+        for word in words {
+            sqlx::query!(
+                "DELETE FROM stopwords WHERE container_id=? AND word=?;",
+                container_id,
+                word
+            )
+            .execute(&mut *transaction)
+            .await?;
+        }
+        transaction.commit().await
+    }
+
+    async fn get_digest_schedules(&self) -> Result<Vec<DigestSchedule>, Error> {
+        let rows = sqlx::query!(
+            "SELECT container_id, channel_id, schedule, last_run_time FROM digest_schedules;"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| DigestSchedule {
+                container_id: row.container_id,
+                channel_id: row.channel_id,
+                schedule: row.schedule,
+                last_run_time: row.last_run_time,
+            })
+            .collect())
+    }
+
+    async fn set_digest_schedule(&self, schedule: &DigestSchedule) -> Result<(), Error> {
+        sqlx::query!(
+            "INSERT INTO digest_schedules (container_id, channel_id, schedule, last_run_time) VALUES (?, ?, ?, ?) ON CONFLICT (container_id) DO UPDATE SET channel_id=excluded.channel_id, schedule=excluded.schedule, last_run_time=excluded.last_run_time;",
+            schedule.container_id,
+            schedule.channel_id,
+            schedule.schedule,
+            schedule.last_run_time
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn remove_digest_schedule(&self, container_id: &str) -> Result<(), Error> {
+        sqlx::query!(
+            "DELETE FROM digest_schedules WHERE container_id=?;",
+            container_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn set_digest_last_run_time(
+        &self,
+        container_id: &str,
+        last_run_time: i64,
+    ) -> Result<(), Error> {
+        sqlx::query!(
+            "UPDATE digest_schedules SET last_run_time=? WHERE container_id=?;",
+            last_run_time,
+            container_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_guild_settings(&self, container_id: &str) -> Result<Option<GuildSettings>, Error> {
+        let result = sqlx::query!(
+            "SELECT container_id, default_context, default_limit, stopwords, locale FROM guild_settings WHERE container_id=?;",
+            container_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result.map(|row| GuildSettings {
+            container_id: row.container_id,
+            default_context: row.default_context,
+            default_limit: row
+                .default_limit
+                .map(|default_limit| u32::try_from(default_limit).unwrap_or(u32::MAX)),
+            stopwords: row.stopwords,
+            locale: row.locale,
+        }))
+    }
+
+    async fn set_guild_settings(&self, settings: &GuildSettings) -> Result<(), Error> {
+        sqlx::query!(
+            "INSERT INTO guild_settings (container_id, default_context, default_limit, stopwords, locale) VALUES (?, ?, ?, ?, ?) ON CONFLICT (container_id) DO UPDATE SET default_context=excluded.default_context, default_limit=excluded.default_limit, stopwords=excluded.stopwords, locale=excluded.locale;",
+            settings.container_id,
+            settings.default_context,
+            settings.default_limit,
+            settings.stopwords,
+            settings.locale
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_excluded_container_ids(&self, container_id: &str) -> Result<Vec<String>, Error> {
+        let rows = sqlx::query!(
+            "SELECT excluded_container_id FROM excluded_containers WHERE container_id=? ORDER BY excluded_container_id ASC;",
+            container_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| row.excluded_container_id)
+            .collect())
+    }
+
+    async fn add_excluded_container_id(
+        &self,
+        container_id: &str,
+        excluded_container_id: &str,
+    ) -> Result<(), Error> {
+        sqlx::query!(
+            "INSERT INTO excluded_containers (container_id, excluded_container_id) VALUES (?, ?) ON CONFLICT (container_id, excluded_container_id) DO NOTHING;",
+            container_id,
+            excluded_container_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn remove_excluded_container_id(
+        &self,
+        container_id: &str,
+        excluded_container_id: &str,
+    ) -> Result<(), Error> {
+        sqlx::query!(
+            "DELETE FROM excluded_containers WHERE container_id=? AND excluded_container_id=?;",
+            container_id,
+            excluded_container_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_opted_out_sender_ids(&self) -> Result<Vec<String>, Error> {
+        let rows = sqlx::query!("SELECT sender_id FROM opted_out_senders;")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.into_iter().map(|row| row.sender_id).collect())
+    }
+
+    async fn add_opted_out_sender_id(&self, sender_id: &str) -> Result<(), Error> {
+        sqlx::query!(
+            "INSERT INTO opted_out_senders (sender_id) VALUES (?) ON CONFLICT (sender_id) DO NOTHING;",
+            sender_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn remove_opted_out_sender_id(&self, sender_id: &str) -> Result<(), Error> {
+        sqlx::query!(
+            "DELETE FROM opted_out_senders WHERE sender_id=?;",
+            sender_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_backfill_cursor(
+        &self,
+        container_id: &str,
+    ) -> Result<Option<BackfillCursor>, Error> {
+        let row = sqlx::query!(
+            "SELECT container_id, oldest_entry_id, newest_entry_id, complete FROM backfill_cursors WHERE container_id=?;",
+            container_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|row| BackfillCursor {
+            container_id: row.container_id,
+            oldest_entry_id: row.oldest_entry_id,
+            newest_entry_id: row.newest_entry_id,
+            complete: row.complete,
+        }))
+    }
+
+    async fn set_backfill_cursor(&self, cursor: &BackfillCursor) -> Result<(), Error> {
+        sqlx::query!(
+            "INSERT INTO backfill_cursors (container_id, oldest_entry_id, newest_entry_id, complete) VALUES (?, ?, ?, ?) ON CONFLICT (container_id) DO UPDATE SET oldest_entry_id=excluded.oldest_entry_id, newest_entry_id=excluded.newest_entry_id, complete=excluded.complete;",
+            cursor.container_id,
+            cursor.oldest_entry_id,
+            cursor.newest_entry_id,
+            cursor.complete
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn remove_backfill_cursor(&self, container_id: &str) -> Result<(), Error> {
+        sqlx::query!(
+            "DELETE FROM backfill_cursors WHERE container_id=?;",
+            container_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_entry_ids_by_sender(&self, sender_id: &str) -> Result<Vec<String>, Error> {
+        let rows = sqlx::query!("SELECT entry_id FROM entries WHERE sender_id=?;", sender_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.into_iter().map(|row| row.entry_id).collect())
+    }
+
+    async fn get_entry_ids_in_container(&self, container_id: &str) -> Result<Vec<String>, Error> {
+        let rows = sqlx::query!(
+            "SELECT entry_id FROM entries WHERE container_id=?;",
+            container_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| row.entry_id).collect())
+    }
+
+    async fn add_ngrams(
+        &self,
+        ngrams: &[NgramForStore],
+        entry_ids: &[String],
+    ) -> Result<(), Error> {
+        let mut transaction = self.pool.begin().await?;
+        let marked_entry_ids = mark_entries_as_ngrams_cached(&mut transaction, entry_ids).await?;
+        insert_ngrams(
+            &mut transaction,
+            &filter_ngrams_by_entry_ids(ngrams, &marked_entry_ids),
+        )
+        .await?;
+        transaction.commit().await
+    }
+
+    async fn add_entries(&self, entries: &[Entry], ngrams: &[NgramForStore]) -> Result<(), Error> {
+        let mut transaction = self.pool.begin().await?;
+        let inserted_entry_ids = insert_entries(&mut transaction, entries, true).await?;
+        insert_ngrams(
+            &mut transaction,
+            &filter_ngrams_by_entry_ids(ngrams, &inserted_entry_ids),
+        )
+        .await?;
+        transaction.commit().await
+    }
+
+    async fn delete_entries(
+        &self,
+        entry_ids: &[String],
+        ngrams: &[NgramForStore],
+    ) -> Result<(), Error> {
+        let mut transaction = self.pool.begin().await?;
+        subtract_ngrams(&mut transaction, ngrams).await?;
+        remove_entries(&mut transaction, entry_ids).await?;
+        transaction.commit().await
+    }
+
+    async fn replace_entries(
+        &self,
+        entries: &[Entry],
+        old_ngrams: &[NgramForStore],
+        ngrams: &[NgramForStore],
+    ) -> Result<(), Error> {
+        let entry_ids: Vec<String> = entries.iter().map(|e| e.entry_id.clone()).collect();
+
+        let mut transaction = self.pool.begin().await?;
+        subtract_ngrams(&mut transaction, old_ngrams).await?;
+        remove_entries(&mut transaction, &entry_ids).await?;
+        let inserted_entry_ids = insert_entries(&mut transaction, entries, true).await?;
+        insert_ngrams(
+            &mut transaction,
+            &filter_ngrams_by_entry_ids(ngrams, &inserted_entry_ids),
+        )
+        .await?;
+        transaction.commit().await
+    }
+
+    async fn get_entries_with_cached_ngrams(
+        &self,
+        entry_ids: &[String],
+    ) -> Result<Vec<Entry>, Error> {
+        let mut entries = Vec::new();
+        for entry_ids_chunk in entry_ids.chunks(CHUNK_SIZE) {
+            let mut query_builder =
+                QueryBuilder::new("SELECT * FROM entries WHERE ngrams_cached=true AND");
+            build_in_clause(&mut query_builder, "entry_id", entry_ids_chunk);
+            query_builder.push(";");
+
+            let rows = query_builder.build().fetch_all(&self.pool).await?;
+            for row in rows {
+                entries.push(Entry {
+                    entry_id: row.get("entry_id"),
+                    container_id: row.get("container_id"),
+                    sender_id: row.get("sender_id"),
+                    unix_timestamp: row.get("unix_timestamp"),
+                    content: row.get("content"),
+                    parent_entry_id: row.get("parent_entry_id"),
+                    author_kind: parse_author_kind(row.get("author_kind"))?,
+                });
+            }
+        }
+
+        Ok(entries)
+    }
+
+    async fn add_container(&self, container: &container::Container) -> Result<(), Error> {
+        sqlx::query!(
+            "INSERT INTO containers (container_id, container_parent_id) SELECT ?, ? WHERE NOT EXISTS (SELECT 1 FROM containers WHERE container_id=?);",
+            container.container_id,
+            container.container_parent_id,
+            container.container_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn set_container_parent(&self, container: &container::Container) -> Result<(), Error> {
+        let mut transaction = self.pool.begin().await?;
+        sqlx::query!(
+            "DELETE FROM containers WHERE container_id=?;",
+            container.container_id
+        )
+        .execute(&mut *transaction)
+        .await?;
+        sqlx::query!(
+            "INSERT INTO containers (container_id, container_parent_id) VALUES (?, ?);",
+            container.container_id,
+            container.container_parent_id
+        )
+        .execute(&mut *transaction)
+        .await?;
+        transaction.commit().await
+    }
+
+    async fn remove_container(&self, container_id: &str) -> Result<(), Error> {
+        sqlx::query!("DELETE FROM containers WHERE container_id=?;", container_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_last_entry_id_in_container(&self, container_id: &str) -> Result<String, Error> {
+        let result =  sqlx::query!(
+            "SELECT entry_id FROM entries WHERE container_id=? ORDER BY unix_timestamp DESC LIMIT 1;",
+            container_id
+        )
+        .fetch_one(&self.pool).await?;
+
+        Ok(result.entry_id)
+    }
+
+    async fn get_first_entry_id_in_container(&self, container_id: &str) -> Result<String, Error> {
+        let result =  sqlx::query!(
+            "SELECT entry_id FROM entries WHERE container_id=? ORDER BY unix_timestamp ASC LIMIT 1;",
+            container_id
+        )
+        .fetch_one(&self.pool).await?;
+
+        Ok(result.entry_id)
+    }
+
+    async fn get_entries_without_cached_ngrams(
+        &self,
+        limit: u32,
+        start_id: Option<String>,
+    ) -> Result<Vec<Entry>, Error> {
+        match start_id {
+            Some(start_id) => {
+                sqlx::query!(
+                    "
+                    SELECT * FROM entries WHERE ngrams_cached=false AND unix_timestamp < (
+                        SELECT unix_timestamp FROM entries WHERE entry_id=?
+                    ) ORDER BY unix_timestamp DESC LIMIT ?;
+                    ",
+                    start_id,
+                    limit
+                )
+                .fetch_all(&self.pool)
+                .await?
+                .into_iter()
+                .map(|row| {
+                    Ok(Entry {
+                        entry_id: row.entry_id,
+                        container_id: row.container_id,
+                        sender_id: row.sender_id,
+                        unix_timestamp: row.unix_timestamp,
+                        content: row.content,
+                        parent_entry_id: row.parent_entry_id,
+                        author_kind: parse_author_kind(&row.author_kind)?,
+                    })
+                })
+                .collect()
+            },
+            None => {
+                sqlx::query!(
+                    "SELECT * FROM entries WHERE ngrams_cached=false ORDER BY unix_timestamp DESC LIMIT ?;",
+                    limit
+                )
+                .fetch_all(&self.pool)
+                .await?
+                .into_iter()
+                .map(|row| {
+                    Ok(Entry {
+                        entry_id: row.entry_id,
+                        container_id: row.container_id,
+                        sender_id: row.sender_id,
+                        unix_timestamp: row.unix_timestamp,
+                        content: row.content,
+                        parent_entry_id: row.parent_entry_id,
+                        author_kind: parse_author_kind(&row.author_kind)?,
+                    })
+                })
+                .collect()
+            },
+        }
+    }
+
+    async fn get_child_container_ids(&self, container_id: &str) -> Result<Vec<String>, Error> {
+        sqlx::query!(
+            "SELECT container_id FROM containers WHERE container_parent_id=?;",
+            container_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map(|rows| rows.into_iter().map(|row| row.container_id).collect())
+    }
+
+    async fn get_ngram_count_in_container(&self, container_id: &str) -> Result<i32, Error> {
+        let result = sqlx::query!(
+            "SELECT COUNT(*) as count FROM ngrams WHERE container_id=?;",
+            container_id
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(result.count)
+    }
+
+    async fn get_entries_count_in_container(&self, container_id: &str) -> Result<i32, Error> {
+        let result = sqlx::query!(
+            "SELECT COUNT(*) as count FROM entries WHERE container_id=?;",
+            container_id
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(result.count)
+    }
+
+    async fn get_ngrams_by_count(
+        &self,
+        filter: &NgramsByCountFilter,
+    ) -> Result<Vec<NgramForByCountCommand>, Error> {
+        let mut query_builder =
+            QueryBuilder::new("SELECT content, SUM(count) as total_count FROM ngrams WHERE true");
+
+        if !filter.container_ids.is_empty() {
+            query_builder.push(" AND ");
+            build_in_clause(
+                &mut query_builder,
+                "container_id",
+                filter.container_ids.as_slice(),
+            );
+        }
+
+        if let Some(sender_id) = &filter.sender_id {
+            query_builder.push(" AND sender_id=");
+            query_builder.push_bind(sender_id);
+        }
+
+        if let Some(length) = &filter.length {
+            query_builder.push(" AND length=");
+            query_builder.push_bind(length);
+        }
+
+        if !filter.contents.is_empty() {
+            query_builder.push(" AND");
+            build_in_clause(&mut query_builder, "content", filter.contents.as_slice());
+        }
+
+        if !filter.stopwords.is_empty() {
+            query_builder.push(" AND NOT");
+            build_in_clause(&mut query_builder, "content", filter.stopwords.as_slice());
+        }
+
+        if let Some(since) = filter.since {
+            query_builder.push(" AND time>=");
+            query_builder.push_bind(since);
+        }
+
+        if let Some(until) = filter.until {
+            query_builder.push(" AND time<");
+            query_builder.push_bind(until);
+        }
+
+        build_author_policy_clause(&mut query_builder, "", &filter.author_policy);
+
+        query_builder.push(" GROUP BY content ORDER BY total_count ");
+
+        match filter.order {
+            filters::Order::Ascending => query_builder.push("ASC"),
+            filters::Order::Descending => query_builder.push("DESC"),
+        };
+
+        query_builder.push(", content ASC LIMIT ");
+        query_builder.push_bind(filter.limit);
+        query_builder.push(" OFFSET ");
+        query_builder.push_bind(filter.offset);
+
+        let ngrams = query_builder
+            .build()
+            .fetch_all(&self.pool)
+            .await
+            .map(|rows| {
+                rows.into_iter()
+                    .map(|row| NgramForByCountCommand {
+                        content: row.get("content"),
+                        count: row.get("total_count"),
+                    })
+                    .collect()
+            })?;
+
+        Ok(ngrams)
+    }
+
+    async fn get_ngram_by_content(
+        &self,
+        filter: &NgramsByContentFilter,
+    ) -> Result<Vec<NgramsForByContentCommand>, Error> {
+        let mut query_builder = QueryBuilder::new("SELECT SUM(count) AS count, ");
+        query_builder.push(get_bucket_time_expression(filter.granularity));
+        query_builder.push(" AS bucket_time FROM ngrams WHERE true");
+
+        if let Some(content) = &filter.content {
+            query_builder.push(" AND content=");
+            query_builder.push_bind(content);
+        }
+
+        if let Some(length) = filter.length {
+            query_builder.push(" AND length=");
+            query_builder.push_bind(length);
+        }
+
+        if !filter.container_ids.is_empty() {
+            query_builder.push(" AND ");
+            build_in_clause(
+                &mut query_builder,
+                "container_id",
+                filter.container_ids.as_slice(),
+            );
+        }
+
+        if let Some(sender_id) = &filter.sender_id {
+            query_builder.push(" AND sender_id=");
+            query_builder.push_bind(sender_id);
+        }
+
+        build_author_policy_clause(&mut query_builder, "", &filter.author_policy);
+
+        query_builder.push(" GROUP BY bucket_time ORDER BY bucket_time ASC;");
+
+        query_builder
+            .build()
+            .fetch_all(&self.pool)
+            .await
+            .map(|rows| {
+                rows.into_iter()
+                    .map(|row| NgramsForByContentCommand {
+                        count: row.get("count"),
+                        time: row.get("bucket_time"),
+                    })
+                    .collect()
+            })
+    }
+
+    async fn get_ngram_total_count(&self, filter: &NgramTotalCountFilter) -> Result<u64, Error> {
+        let mut query_builder = QueryBuilder::new(
+            "SELECT COALESCE(SUM(count), 0) AS total_count FROM ngrams WHERE true",
+        );
+
+        if let Some(length) = filter.length {
+            query_builder.push(" AND length=");
+            query_builder.push_bind(length);
+        }
+
+        if !filter.container_ids.is_empty() {
+            query_builder.push(" AND ");
+            build_in_clause(
+                &mut query_builder,
+                "container_id",
+                filter.container_ids.as_slice(),
+            );
+        }
+
+        if let Some(sender_id) = &filter.sender_id {
+            query_builder.push(" AND sender_id=");
+            query_builder.push_bind(sender_id);
+        }
+
+        build_author_policy_clause(&mut query_builder, "", &filter.author_policy);
+
+        query_builder.push(";");
+
+        let row = query_builder.build().fetch_one(&self.pool).await?;
+
+        Ok(u64::try_from(row.get::<i64, _>("total_count")).unwrap_or_default())
+    }
+
+    async fn get_reply_pairs(
+        &self,
+        filter: &RepliesFilter,
+    ) -> Result<Vec<ReplyPairForRepliesCommand>, Error> {
+        let mut query_builder = QueryBuilder::new(
+            "
+            SELECT entries.sender_id AS sender_id, parents.sender_id AS parent_sender_id, COUNT(*) AS count
+            FROM entries JOIN entries AS parents ON entries.parent_entry_id = parents.entry_id
+            WHERE entries.sender_id != parents.sender_id
+            ",
+        );
+
+        if !filter.container_ids.is_empty() {
+            query_builder.push(" AND ");
+            build_in_clause(
+                &mut query_builder,
+                "entries.container_id",
+                filter.container_ids.as_slice(),
+            );
+        }
+
+        build_author_policy_clause(&mut query_builder, "entries.", &filter.author_policy);
+        build_author_policy_clause(&mut query_builder, "parents.", &filter.author_policy);
+
+        query_builder
+            .push(" GROUP BY entries.sender_id, parents.sender_id ORDER BY count DESC LIMIT ");
+        query_builder.push_bind(filter.limit);
+
+        query_builder
+            .build()
+            .fetch_all(&self.pool)
+            .await
+            .map(|rows| {
+                rows.into_iter()
+                    .map(|row| ReplyPairForRepliesCommand {
+                        sender_id: row.get("sender_id"),
+                        parent_sender_id: row.get("parent_sender_id"),
+                        count: row.get("count"),
+                    })
+                    .collect()
+            })
+    }
+
+    async fn get_active_senders(
+        &self,
+        filter: &ActiveSendersFilter,
+    ) -> Result<Vec<SenderForDigestCommand>, Error> {
+        let mut query_builder =
+            QueryBuilder::new("SELECT sender_id, COUNT(*) AS count FROM entries WHERE true");
+
+        if !filter.container_ids.is_empty() {
+            query_builder.push(" AND ");
+            build_in_clause(
+                &mut query_builder,
+                "container_id",
+                filter.container_ids.as_slice(),
+            );
+        }
+
+        if let Some(since) = filter.since {
+            query_builder.push(" AND unix_timestamp >= ");
+            query_builder.push_bind(since);
+        }
+
+        build_author_policy_clause(&mut query_builder, "", &filter.author_policy);
+
+        query_builder.push(" GROUP BY sender_id ORDER BY count DESC, sender_id ASC LIMIT ");
+        query_builder.push_bind(filter.limit);
+
+        query_builder
+            .build()
+            .fetch_all(&self.pool)
+            .await
+            .map(|rows| {
+                rows.into_iter()
+                    .map(|row| SenderForDigestCommand {
+                        sender_id: row.get("sender_id"),
+                        count: row.get("count"),
+                    })
+                    .collect()
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Sql;
+    use crate::processor::tokenizer::TokenizerKind;
+    use crate::processor::Processor;
+    use crate::store::scenarios;
+    use std::path::{Path, PathBuf};
+
+    const PREFIX: &str = "sql";
+
+    fn get_database_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "nlp_bot_api_test_{}_{}.db",
+            name,
+            std::process::id()
+        ))
+    }
+
+    fn remove_database(path: &Path) {
+        for suffix in ["", "-wal", "-shm"] {
+            let mut file_path = path.as_os_str().to_owned();
+            file_path.push(suffix);
+            let _ = std::fs::remove_file(file_path);
+        }
+    }
+
+    /// Each test gets a new database, which is removed by [`remove_database`]
+    async fn make_processor(path: &Path) -> Processor<Sql> {
+        remove_database(path);
+        let store = Sql::new(path.to_str().expect("Invalid database path"))
+            .await
+            .expect("Failed to construct store");
+
+        Processor::new(Box::new(store), TokenizerKind::default().into_tokenizer())
+            .with_author_policy(scenarios::make_author_policy())
+    }
+
+    #[tokio::test]
+    async fn add_entries() {
+        let path = get_database_path("add_entries");
+        scenarios::add_entries(&make_processor(&path).await, PREFIX).await;
+        remove_database(&path);
+    }
+
+    #[tokio::test]
+    async fn cache_ngrams() {
+        let path = get_database_path("cache_ngrams");
+        scenarios::cache_ngrams(&make_processor(&path).await, PREFIX).await;
+        remove_database(&path);
+    }
+
+    #[tokio::test]
+    async fn get_ngrams_by_count() {
+        let path = get_database_path("get_ngrams_by_count");
+        scenarios::get_ngrams_by_count(&make_processor(&path).await, PREFIX).await;
+        remove_database(&path);
+    }
+
+    #[tokio::test]
+    async fn delete_entries() {
+        let path = get_database_path("delete_entries");
+        scenarios::delete_entries(&make_processor(&path).await, PREFIX).await;
+        remove_database(&path);
+    }
+
+    #[tokio::test]
+    async fn update_entry() {
+        let path = get_database_path("update_entry");
+        scenarios::update_entry(&make_processor(&path).await, PREFIX).await;
+        remove_database(&path);
+    }
+
+    #[tokio::test]
+    async fn opt_out_sender() {
+        let path = get_database_path("opt_out_sender");
+        scenarios::opt_out_sender(&make_processor(&path).await, PREFIX).await;
+        remove_database(&path);
+    }
+
+    #[tokio::test]
+    async fn exclude_containers() {
+        let path = get_database_path("exclude_containers");
+        scenarios::exclude_containers(&make_processor(&path).await, PREFIX).await;
+        remove_database(&path);
+    }
+
+    #[tokio::test]
+    async fn reclassify_authors() {
+        let path = get_database_path("reclassify_authors");
+        scenarios::reclassify_authors(&make_processor(&path).await, PREFIX).await;
+        remove_database(&path);
+    }
+}
@@ -1,10 +1,16 @@
-use sqlx::{query_builder::QueryBuilder, Sqlite};
+use crate::processor::entry::{AuthorKind, AuthorPolicy};
+use crate::processor::ngram::NgramForStore;
+use sqlx::{query_builder::QueryBuilder, Database, Encode, Error, Type};
+use std::collections::HashSet;
+use std::str::FromStr;
 
-pub fn build_in_clause(
-    query_builder: &mut QueryBuilder<'_, Sqlite>,
+pub fn build_in_clause<'a, DB: Database>(
+    query_builder: &mut QueryBuilder<'a, DB>,
     column: &str,
     values: &[String],
-) {
+) where
+    String: 'a + Encode<'a, DB> + Send + Type<DB>,
+{
     query_builder.push(" ");
     query_builder.push(column);
     query_builder.push(" IN ");
@@ -19,3 +25,56 @@ pub fn build_in_clause(
     }
     query_builder.push(") ");
 }
+
+/// Restricts the rows to the ones the policy allows. The `author_kind` and
+/// `sender_id` columns are prefixed with `table_prefix`, e.g. `parents.`.
+pub fn build_author_policy_clause<'a, DB: Database>(
+    query_builder: &mut QueryBuilder<'a, DB>,
+    table_prefix: &str,
+    author_policy: &AuthorPolicy,
+) where
+    String: 'a + Encode<'a, DB> + Send + Type<DB>,
+{
+    if author_policy.ignored_author_kinds.is_empty() {
+        return;
+    }
+
+    let ignored_author_kinds: Vec<String> = author_policy
+        .ignored_author_kinds
+        .iter()
+        .map(|author_kind| String::from(author_kind.as_str()))
+        .collect();
+    query_builder.push(" AND (NOT");
+    build_in_clause(
+        query_builder,
+        &format!("{}author_kind", table_prefix),
+        &ignored_author_kinds,
+    );
+
+    if !author_policy.allowed_sender_ids.is_empty() {
+        query_builder.push(" OR");
+        build_in_clause(
+            query_builder,
+            &format!("{}sender_id", table_prefix),
+            &author_policy.allowed_sender_ids,
+        );
+    }
+    query_builder.push(")");
+}
+
+pub fn parse_author_kind(author_kind: &str) -> Result<AuthorKind, Error> {
+    AuthorKind::from_str(author_kind).map_err(|e| Error::Decode(e.into()))
+}
+
+/// Returns the n-grams which come from one of the given entries
+pub fn filter_ngrams_by_entry_ids<'a>(
+    ngrams: &'a [NgramForStore],
+    entry_ids: &[String],
+) -> Vec<&'a NgramForStore> {
+    let entry_ids: HashSet<&str> = entry_ids.iter().map(String::as_str).collect();
+
+    ngrams
+        .iter()
+        .filter(|ngram| entry_ids.contains(ngram.entry_id.as_str()))
+        .collect()
+}
@@ -0,0 +1,171 @@
+use nlp_bot_api::displayers::ascii_table::display_ngram_list;
+use nlp_bot_api::displayers::chart::display_ngram_count_over_time;
+use nlp_bot_api::displayers::locale::Locale;
+use nlp_bot_api::processor::ngram::{Granularity, NgramSeriesForByContentCommand};
+use nlp_bot_api::processor::stopwords::Language;
+use nlp_bot_api::processor::tokenizer::TokenizerKind;
+use nlp_bot_api::processor::Processor;
+use nlp_bot_api::store::filters::Order;
+
+#[derive(clap::Args, Debug)]
+pub struct NgramsByCountArguments {
+    /// Look for n-grams sent by this sender, as stored (e.g. `<@123>`)
+    #[arg(long)]
+    sender: Option<String>,
+    /// Length of the n-grams to look for
+    #[arg(long)]
+    length: Option<u32>,
+    /// The amount of n-grams to get
+    #[arg(long)]
+    limit: Option<u32>,
+    /// Either `asc` or `desc`
+    #[arg(long)]
+    order: Option<Order>,
+    /// Look for n-grams sent in this container and its children. Can be
+    /// repeated.
+    #[arg(long = "container")]
+    container_ids: Vec<String>,
+    /// Exclude stop-words of this language. Either `en`, `cs`, `es` or `de`.
+    #[arg(long)]
+    stopwords: Option<Language>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct NgramByContentArguments {
+    /// The n-grams about which to get data
+    #[arg(required = true)]
+    ngrams: Vec<String>,
+    /// Look for n-grams sent by this sender, as stored (e.g. `<@123>`)
+    #[arg(long)]
+    sender: Option<String>,
+    /// Look for n-grams sent in this container and its children. Can be
+    /// repeated.
+    #[arg(long = "container")]
+    container_ids: Vec<String>,
+    /// Either `hour`, `day`, `week` or `month`
+    #[arg(long)]
+    granularity: Option<Granularity>,
+    /// Show the occurrences per 1,000 tokens instead of the number of
+    /// occurrences
+    #[arg(long)]
+    normalise: bool,
+    /// The locale of the dates in the chart
+    #[arg(long)]
+    locale: Option<Locale>,
+    /// The path of the PNG file the chart is written to
+    #[arg(short, long, default_value = "./chart.png")]
+    output: String,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct StatsArguments {
+    /// Count in this container and its children
+    #[arg(long = "container", default_value = "discord")]
+    container_id: String,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct RebuildArguments {
+    /// The tokenizer to rebuild the n-grams with. Either `unicode` or
+    /// `legacy`. It should match the one the bot is configured with.
+    #[arg(long, default_value = "unicode")]
+    pub tokenizer: TokenizerKind,
+}
+
+pub async fn ngrams_by_count(
+    processor: &Processor,
+    arguments: NgramsByCountArguments,
+) -> Result<(), String> {
+    let stopwords = processor
+        .get_stopwords(arguments.stopwords, None)
+        .await
+        .map_err(|e| e.to_string())?;
+    let ngrams = processor
+        .get_ngrams_by_count(
+            arguments.sender,
+            arguments.length,
+            arguments.limit,
+            &arguments.container_ids,
+            arguments.order,
+            &stopwords,
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+
+    println!("{}", display_ngram_list(&ngrams));
+
+    Ok(())
+}
+
+pub async fn ngram_by_content(
+    processor: &Processor,
+    arguments: NgramByContentArguments,
+) -> Result<(), String> {
+    let ngram_series: Vec<NgramSeriesForByContentCommand> = processor
+        .get_ngrams_by_content(
+            &arguments.ngrams,
+            arguments.sender,
+            &arguments.container_ids,
+            arguments.granularity,
+            arguments.normalise,
+        )
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|series| !series.ngrams.is_empty())
+        .collect();
+    if ngram_series.is_empty() {
+        return Err(String::from("No n-grams found!"));
+    }
+
+    let image = display_ngram_count_over_time(
+        &ngram_series,
+        arguments.granularity.unwrap_or_default(),
+        arguments.normalise,
+        arguments.locale,
+    )
+    .map_err(|e| e.to_string())?;
+    std::fs::write(&arguments.output, image).map_err(|e| e.to_string())?;
+
+    println!("Wrote the chart to {}", arguments.output);
+
+    Ok(())
+}
+
+pub async fn stats(processor: &Processor, arguments: StatsArguments) -> Result<(), String> {
+    let entries_count = processor
+        .get_entries_count_in_container(&arguments.container_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let ngram_occurrences = processor
+        .get_ngram_occurrences_in_container(&arguments.container_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let ngram_count = processor
+        .get_ngram_count_in_container(&arguments.container_id)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    println!("Entries: {}", entries_count);
+    println!("N-gram occurrences: {}", ngram_occurrences);
+    // N-grams are stored per sender, container and hour
+    println!("Stored n-gram counts: {}", ngram_count);
+
+    Ok(())
+}
+
+pub async fn rebuild(processor: &Processor) -> Result<(), String> {
+    // Stores the tokenizer, so that the bot keeps the rebuilt n-grams
+    processor
+        .reset_ngrams_if_tokenizer_changed()
+        .await
+        .map_err(|e| e.to_string())?;
+    processor
+        .rebuild_ngrams()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    println!("Rebuilt the n-grams");
+
+    Ok(())
+}
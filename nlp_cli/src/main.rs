@@ -0,0 +1,72 @@
+mod commands;
+
+use clap::Parser;
+use commands::{NgramByContentArguments, NgramsByCountArguments, RebuildArguments, StatsArguments};
+use nlp_bot_api::processor::tokenizer::TokenizerKind;
+use nlp_bot_api::processor::Processor;
+use nlp_bot_api::store::Sql;
+use std::path::Path;
+
+/// Query the database of the bot without Discord
+#[derive(clap::Parser, Debug)]
+struct CommandLineArguments {
+    /// The path to the `SQLite` database of the bot
+    #[arg(short, long, default_value = "./database.db")]
+    database_path: String,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Print the most or least used n-grams as a table
+    NgramsByCount(NgramsByCountArguments),
+    /// Draw a chart of the occurrences of n-grams over time
+    NgramByContent(NgramByContentArguments),
+    /// Print the number of stored entries and n-gram occurrences
+    Stats(StatsArguments),
+    /// Drop the n-grams and recompute them from the stored entries
+    Rebuild(RebuildArguments),
+}
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+
+    let command_line_arguments = CommandLineArguments::parse();
+
+    // Opening a missing database would create an empty one
+    if !Path::new(&command_line_arguments.database_path).exists() {
+        println!(
+            "Database {} does not exist",
+            command_line_arguments.database_path
+        );
+        return;
+    }
+    let store = match Sql::new(&command_line_arguments.database_path).await {
+        Ok(store) => store,
+        Err(e) => {
+            println!("Failed to construct store: {}", e);
+            return;
+        }
+    };
+
+    // The tokenizer is only used when the n-grams are rebuilt
+    let tokenizer_kind = match &command_line_arguments.command {
+        Command::Rebuild(arguments) => arguments.tokenizer.clone(),
+        _ => TokenizerKind::default(),
+    };
+    let processor: Processor = Processor::new(Box::new(store), tokenizer_kind.into_tokenizer());
+
+    let result = match command_line_arguments.command {
+        Command::NgramsByCount(arguments) => commands::ngrams_by_count(&processor, arguments).await,
+        Command::NgramByContent(arguments) => {
+            commands::ngram_by_content(&processor, arguments).await
+        }
+        Command::Stats(arguments) => commands::stats(&processor, arguments).await,
+        Command::Rebuild(_) => commands::rebuild(&processor).await,
+    };
+    if let Err(e) = result {
+        println!("{}", e);
+    }
+}